@@ -0,0 +1,56 @@
+// Trains the neural network player from the perfect solver, saves its weights, and plays
+// it against the rule-based computer player.
+//
+//     cargo run --release --example train_network [weights file]
+use std::env;
+use tic_tac_toe::computer::ComputerPlayer;
+use tic_tac_toe::game::Player;
+use tic_tac_toe::neural::{solver_examples, Network, NeuralPlayer};
+use tic_tac_toe::solver::Solver;
+use tic_tac_toe::{play, Playable};
+
+fn main() {
+    let path = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "network.txt".to_string());
+
+    let examples = solver_examples(&mut Solver::new());
+    println!("{} positions labelled by the solver", examples.len());
+
+    let mut network = Network::new(128, 1);
+    let mut rate = 0.01;
+    for round in 0..8 {
+        let loss = network.train(&examples, 50, rate, round);
+        println!(
+            "epoch {}: loss {:.3}, best move right in {:.1}% of positions",
+            (round + 1) * 50,
+            loss,
+            network.accuracy(&examples) * 100.0,
+        );
+        rate *= 0.6;
+    }
+
+    network.save(&path).expect("Couldn't save the network");
+    println!("Saved weights to {}", path);
+
+    for player in [Player::X, Player::O].iter() {
+        let mut results = [0; 3];
+        for _ in 0..1000 {
+            let neural: Box<dyn Playable> = Box::new(NeuralPlayer::new(network.clone(), *player));
+            let computer: Box<dyn Playable> = Box::new(ComputerPlayer::new_silent(player.turn()));
+            let grid = match player {
+                Player::X => play(neural, computer),
+                _ => play(computer, neural),
+            };
+            results[match grid.get_winner() {
+                Some(winner) if winner == *player => 0,
+                None => 1,
+                Some(_) => 2,
+            }] += 1;
+        }
+        println!(
+            "As {} against ComputerPlayer: {} wins, {} draws, {} losses",
+            player, results[0], results[1], results[2],
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use super::game::Space;
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Scores a position that the search didn't have time to play out, from the point of view
+// of the player given. Positive is good for them. The search caps scores at half the
+// score of a win, so no evaluation is ever mistaken for a forced result.
+pub trait Evaluator {
+    fn evaluate(&self, grid: &Grid, player: &Player) -> i32;
+}
+
+// Counts the lines each side could still complete, weighting each by how many of its
+// spaces are already filled: a line with n stones in it is worth 4^n.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpenLines;
+
+impl OpenLines {
+    fn weight(stones: usize) -> i32 {
+        1 << (2 * stones.min(12))
+    }
+}
+
+impl Evaluator for OpenLines {
+    fn evaluate(&self, grid: &Grid, player: &Player) -> i32 {
+        let mut score = 0;
+
+        for line in grid.lines() {
+            let spaces = line.get_spaces();
+            let owners: Vec<Option<Player>> = spaces
+                .iter()
+                .filter(|s| **s != Space::Empty)
+                .map(|s| s.get_player())
+                .collect();
+
+            // A line that holds stones of both sides, or a blocked space, is dead.
+            match owners.first() {
+                None => {}
+                Some(Some(owner)) if owners.iter().all(|o| *o == Some(*owner)) => {
+                    if owner == player {
+                        score += Self::weight(owners.len());
+                    } else {
+                        score -= Self::weight(owners.len());
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        score
+    }
+}
+
+// Random keys for every symbol on every space, XORed together to identify a position.
+struct Zobrist {
+    keys: Vec<[u64; 4]>,
+    side: u64,
+}
+
+impl Zobrist {
+    fn new(spaces: usize) -> Self {
+        // A fixed seed keeps searches reproducible.
+        let mut rng = StdRng::seed_from_u64(0);
+        Self {
+            keys: (0..spaces).map(|_| rng.gen()).collect(),
+            side: rng.gen(),
+        }
+    }
+
+    fn key(&self, grid: &Grid, coordinate: &Coordinate, player: &Player) -> u64 {
+        let index = coordinate.y() * grid.width() + coordinate.x();
+        self.keys[index][Player::ALL.iter().position(|p| p == player).unwrap()]
+    }
+
+    fn hash(&self, grid: &Grid, to_move: &Player) -> u64 {
+        let side = if *to_move == Player::O { self.side } else { 0 };
+        grid.coordinates()
+            .filter_map(|c| {
+                grid.get_space(&c)
+                    .get_player()
+                    .map(|p| self.key(grid, &c, &p))
+            })
+            .fold(side, |hash, key| hash ^ key)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Bound {
+    Exact,
+    // The search failed high, so the value is at least this much.
+    Lower,
+    // The search failed low, so the value is at most this much.
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    depth: usize,
+    value: i32,
+    bound: Bound,
+    best: Option<Coordinate>,
+}
+
+// Negamax search with alpha-beta pruning, for two-player k-in-a-row on boards too big to
+// solve outright. It deepens one ply at a time until it reaches its depth limit or runs
+// through its node budget, and plays the best move from the deepest search it finished.
+// Positions it has already searched are kept in a transposition table, keyed by Zobrist
+// hash, across moves and across games on copies of the same board. The table starts
+// again whenever the player is handed a board with different winning sets.
+pub struct AlphaBetaPlayer {
+    player: Player,
+    depth: usize,
+    node_budget: usize,
+    evaluator: Box<dyn Evaluator>,
+    // The board the table was built for
+    board: Option<Grid>,
+    zobrist: Option<Rc<Zobrist>>,
+    table: HashMap<u64, Entry>,
+    nodes: usize,
+}
+
+impl AlphaBetaPlayer {
+    const WIN: i32 = 1_000_000;
+    const DEFAULT_DEPTH: usize = 4;
+    const DEFAULT_NODE_BUDGET: usize = 100_000;
+
+    pub fn new(player: Player) -> Self {
+        assert!(
+            player == Player::X || player == Player::O,
+            "Alpha-beta search is for X and O only",
+        );
+        Self {
+            player,
+            depth: Self::DEFAULT_DEPTH,
+            node_budget: Self::DEFAULT_NODE_BUDGET,
+            evaluator: Box::new(OpenLines),
+            board: None,
+            zobrist: None,
+            table: HashMap::new(),
+            nodes: 0,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "The search must look at least one move ahead");
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_node_budget(mut self, node_budget: usize) -> Self {
+        self.node_budget = node_budget;
+        self
+    }
+
+    pub fn with_evaluator(mut self, evaluator: Box<dyn Evaluator>) -> Self {
+        self.evaluator = evaluator;
+        self
+    }
+
+    // How many positions the last move's search visited.
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    // Wins found sooner score higher, so the player takes the quickest win and puts off
+    // losses as long as it can. Win scores are stored relative to the position they were
+    // found from, since the same position can come up at different plies.
+    fn to_table(value: i32, ply: usize) -> i32 {
+        if value.abs() > Self::WIN / 2 {
+            value + value.signum() * ply as i32
+        } else {
+            value
+        }
+    }
+
+    fn from_table(value: i32, ply: usize) -> i32 {
+        if value.abs() > Self::WIN / 2 {
+            value - value.signum() * ply as i32
+        } else {
+            value
+        }
+    }
+
+    // The remembered best move first, then wins, then blocks, then spaces on the most
+    // lines still open to the player.
+    fn ordered_moves(grid: &Grid, player: &Player, best: Option<Coordinate>) -> Vec<Coordinate> {
+        let opponent = player.turn();
+        let mut scored: Vec<(i32, Coordinate)> = grid
+            .legal_moves()
+            .into_iter()
+            .map(|c| {
+                let score = if Some(c) == best {
+                    i32::MAX
+                } else if grid.wins_with(&c, player) {
+                    i32::MAX - 1
+                } else if grid.wins_with(&c, &opponent) {
+                    i32::MAX - 2
+                } else {
+                    grid.lines_through(&c)
+                        .filter(|line| {
+                            line.get_spaces()
+                                .iter()
+                                .all(|s| s.get_player().is_none_or(|p| p == *player))
+                        })
+                        .count() as i32
+                };
+                (score, c)
+            })
+            .collect();
+
+        // A stable sort keeps ties in board order, so searches are reproducible.
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        scored.into_iter().map(|(_, c)| c).collect()
+    }
+
+    // Returns `None` if the node budget ran out before the search finished.
+    #[allow(clippy::too_many_arguments)]
+    fn negamax(
+        &mut self,
+        grid: &mut Grid,
+        player: &Player,
+        depth: usize,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+        hash: u64,
+    ) -> Option<(i32, Option<Coordinate>)> {
+        if self.nodes >= self.node_budget {
+            return None;
+        }
+        self.nodes += 1;
+
+        let original_alpha = alpha;
+        let remembered = self.table.get(&hash).copied();
+        if let Some(entry) = remembered {
+            if entry.depth >= depth {
+                let value = Self::from_table(entry.value, ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => value >= beta,
+                    Bound::Upper => value <= alpha,
+                };
+                if cutoff {
+                    return Some((value, entry.best));
+                }
+            }
+        }
+
+        if depth == 0 {
+            let limit = Self::WIN / 2;
+            let value = self.evaluator.evaluate(grid, player).clamp(-limit, limit);
+            return Some((value, None));
+        }
+
+        let moves = Self::ordered_moves(grid, player, remembered.and_then(|e| e.best));
+        if moves.is_empty() {
+            return Some((0, None));
+        }
+
+        let zobrist = Rc::clone(self.zobrist.as_ref().unwrap());
+        let mut best: (i32, Option<Coordinate>) = (i32::MIN, None);
+
+        for coordinate in moves {
+            let value = if grid.wins_with(&coordinate, player) {
+                Self::WIN - ply as i32
+            } else {
+                let next_hash = hash ^ zobrist.key(grid, &coordinate, player) ^ zobrist.side;
+                grid.set_space(&coordinate, player).ok();
+                let child = self.negamax(
+                    grid,
+                    &player.turn(),
+                    depth - 1,
+                    -beta,
+                    -alpha,
+                    ply + 1,
+                    next_hash,
+                );
+                grid.clear_space(&coordinate);
+                -child?.0
+            };
+
+            if value > best.0 {
+                best = (value, Some(coordinate));
+            }
+            alpha = alpha.max(value);
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best.0 <= original_alpha {
+            Bound::Upper
+        } else if best.0 >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.insert(
+            hash,
+            Entry {
+                depth,
+                value: Self::to_table(best.0, ply),
+                bound,
+                best: best.1,
+            },
+        );
+
+        Some(best)
+    }
+}
+
+impl Playable for AlphaBetaPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        assert!(
+            !grid.has_captures(),
+            "Alpha-beta search doesn't handle captures",
+        );
+        if self.board.as_ref().is_none_or(|b| !b.shares_lines(grid)) {
+            self.board = Some(grid.clone());
+            self.zobrist = Some(Rc::new(Zobrist::new(grid.width() * grid.height())));
+            self.table.clear();
+        }
+
+        let player = self.player;
+        let hash = self.zobrist.as_ref().unwrap().hash(grid, &player);
+
+        let mut grid = grid.clone();
+        let mut best = None;
+        self.nodes = 0;
+
+        for depth in 1..=self.depth {
+            match self.negamax(&mut grid, &player, depth, -i32::MAX, i32::MAX, 0, hash) {
+                Some((value, coordinate)) => {
+                    best = coordinate.or(best);
+                    // No point searching deeper once the result is certain.
+                    if value.abs() > Self::WIN / 2 {
+                        break;
+                    }
+                }
+                None => break,
+            }
+        }
+
+        best.or_else(|| Self::ordered_moves(&grid, &player, None).first().copied())
+            .expect("No legal moves!")
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+}
+
+#[cfg(test)]
+mod test_alpha_beta {
+    use super::{AlphaBetaPlayer, Evaluator, OpenLines, Zobrist};
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::{play_game, Playable, WinRule};
+
+    #[test]
+    fn takes_win_and_blocks() {
+        // X..
+        // OO.
+        // X..
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+
+        let mut player = AlphaBetaPlayer::new(Player::X);
+        assert_eq!(Coordinate::new(2, 1), player.play(&grid));
+
+        // With nothing to block, O takes the row.
+        let mut player = AlphaBetaPlayer::new(Player::O);
+        assert_eq!(Coordinate::new(2, 1), player.play(&grid));
+    }
+
+    #[test]
+    fn never_loses_at_full_depth() {
+        for seed in 0..5 {
+            let standings = play_game(
+                Grid::empty(),
+                vec![
+                    (Player::X, Box::new(RandoPlayer::seeded(seed))),
+                    (
+                        Player::O,
+                        Box::new(AlphaBetaPlayer::new(Player::O).with_depth(9)),
+                    ),
+                ],
+                WinRule::FirstToLine,
+            );
+            assert_ne!(Some(Player::X), standings.get_winner());
+        }
+    }
+
+    #[test]
+    fn respects_node_budget() {
+        let mut grid = Grid::with_size(9, 9, 5);
+        grid.set_space(&Coordinate::new(4, 4), &Player::X).unwrap();
+
+        let mut player = AlphaBetaPlayer::new(Player::O)
+            .with_depth(6)
+            .with_node_budget(200);
+        let coordinate = player.play(&grid);
+        assert!(grid.is_legal(&coordinate));
+        assert!(player.nodes() <= 200);
+    }
+
+    #[test]
+    fn custom_evaluator() {
+        // Only cares about having more stones in column A.
+        struct ColumnA;
+
+        impl Evaluator for ColumnA {
+            fn evaluate(&self, grid: &Grid, player: &Player) -> i32 {
+                let count = |p: &Player| {
+                    (0..grid.height())
+                        .filter(|y| {
+                            grid.get_space(&Coordinate::new(0, *y)).get_player() == Some(*p)
+                        })
+                        .count() as i32
+                };
+                count(player) - count(&player.turn())
+            }
+        }
+
+        let mut player = AlphaBetaPlayer::new(Player::X)
+            .with_depth(1)
+            .with_evaluator(Box::new(ColumnA));
+        assert_eq!(0, player.play(&Grid::with_size(6, 6, 4)).x());
+    }
+
+    #[test]
+    fn clamps_evaluation() {
+        // An evaluation past the win scores must not end the search early.
+        struct Huge;
+
+        impl Evaluator for Huge {
+            fn evaluate(&self, _grid: &Grid, _player: &Player) -> i32 {
+                10 * AlphaBetaPlayer::WIN
+            }
+        }
+
+        let nodes = |depth| {
+            let mut player = AlphaBetaPlayer::new(Player::X)
+                .with_depth(depth)
+                .with_evaluator(Box::new(Huge));
+            player.play(&Grid::with_size(4, 4, 3));
+            player.nodes()
+        };
+        assert!(nodes(3) > nodes(1));
+    }
+
+    #[test]
+    fn new_lines_new_table() {
+        // X wins with C1 on the classic board, but with A3 on a board whose only winning
+        // set is A1 and A3. The same stones must not share a table entry.
+        // X X .
+        // O O .
+        // . . .
+        let stones = |mut grid: Grid| {
+            grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+            grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+            grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+            grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+            grid
+        };
+        let classic = stones(Grid::empty());
+        let column = stones(Grid::with_winning_sets(
+            3,
+            3,
+            vec![vec![Coordinate::new(0, 0), Coordinate::new(0, 2)]],
+        ));
+
+        let mut player = AlphaBetaPlayer::new(Player::X);
+        assert_eq!(Coordinate::new(2, 0), player.play(&classic));
+        assert_eq!(Coordinate::new(0, 2), player.play(&column));
+    }
+
+    #[test]
+    fn open_lines() {
+        let mut grid = Grid::empty();
+        assert_eq!(0, OpenLines.evaluate(&grid, &Player::X));
+
+        // The centre sits on four open lines, worth 4 each.
+        grid.set_space(&Coordinate::new(1, 1), &Player::X).unwrap();
+        assert_eq!(16, OpenLines.evaluate(&grid, &Player::X));
+        assert_eq!(-16, OpenLines.evaluate(&grid, &Player::O));
+
+        // O's corner kills the diagonal and opens two lines of its own.
+        grid.set_space(&Coordinate::new(0, 0), &Player::O).unwrap();
+        assert_eq!(12 - 8, OpenLines.evaluate(&grid, &Player::X));
+    }
+
+    #[test]
+    fn zobrist_updates() {
+        let zobrist = Zobrist::new(9);
+        let mut grid = Grid::empty();
+        let empty = zobrist.hash(&grid, &Player::X);
+        let centre = Coordinate::new(1, 1);
+
+        grid.set_space(&centre, &Player::X).unwrap();
+        assert_eq!(
+            empty ^ zobrist.key(&grid, &centre, &Player::X) ^ zobrist.side,
+            zobrist.hash(&grid, &Player::O),
+        );
+        assert_ne!(empty, zobrist.hash(&grid, &Player::O));
+    }
+
+    #[test]
+    fn beats_random_player_on_large_board() {
+        let standings = play_game(
+            Grid::with_size(7, 7, 4),
+            vec![
+                (
+                    Player::X,
+                    Box::new(
+                        AlphaBetaPlayer::new(Player::X)
+                            .with_depth(3)
+                            .with_node_budget(500),
+                    ),
+                ),
+                (Player::O, Box::new(RandoPlayer::seeded(1))),
+            ],
+            WinRule::FirstToLine,
+        );
+        assert_eq!(Some(Player::X), standings.get_winner());
+    }
+}
@@ -0,0 +1,597 @@
+use super::file::FileError;
+use super::solver::Solver;
+use super::symmetry::{canonical, is_symmetric, key, Symmetry};
+use super::{Coordinate, Grid, Playable, Player, Reply, Standings};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// An opening book: for each position it knows, the moves to prefer, each with a weight
+// and the record of the games it was played in. Positions that are turns or flips of
+// each other share an entry, as in `Menace`, so a book for one corner opening covers all
+// four, as long as the board's winning sets turn with it. A book is for one size of
+// board; on other boards it has nothing to say.
+//
+// Books are generated from the solver's best moves or from the games in a database, and
+// `BookPlayer` lets any player follow one before thinking for itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Book {
+    width: usize,
+    height: usize,
+    // Moves on the canonical board, by canonical key
+    positions: HashMap<String, Vec<Entry>>,
+}
+
+// One move in a position, with its weight and its record for the player making it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    coordinate: Coordinate,
+    weight: u32,
+    games: u32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Entry {
+    fn new(coordinate: Coordinate) -> Self {
+        Self {
+            coordinate,
+            weight: 0,
+            games: 0,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    pub fn get_coordinate(&self) -> Coordinate {
+        self.coordinate
+    }
+
+    // How often the move is played, relative to the other moves in the position
+    pub fn get_weight(&self) -> u32 {
+        self.weight
+    }
+
+    pub fn get_games(&self) -> u32 {
+        self.games
+    }
+
+    pub fn get_wins(&self) -> u32 {
+        self.wins
+    }
+
+    pub fn get_draws(&self) -> u32 {
+        self.draws
+    }
+
+    pub fn get_losses(&self) -> u32 {
+        self.losses
+    }
+}
+
+impl Book {
+    const WIN_WEIGHT: u32 = 2;
+    const DRAW_WEIGHT: u32 = 1;
+
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            positions: HashMap::new(),
+        }
+    }
+
+    // Every best move, according to the solver, in every position up to `plies` moves
+    // after `grid`, whichever moves led there. Each best move gets a weight of 1. The
+    // solver only handles two players, so X is taken to move whenever O has as many
+    // stones.
+    pub fn from_solver(grid: &Grid, plies: usize, solver: &mut Solver) -> Self {
+        let mut book = Self::new(grid.width(), grid.height());
+        let mut seen = HashSet::new();
+        let mut positions = vec![(grid.clone(), 0)];
+
+        while let Some((grid, depth)) = positions.pop() {
+            if depth >= plies || !grid.is_in_progress() || !seen.insert(book.key(&grid).0) {
+                continue;
+            }
+
+            let player = if grid.count(&Player::X) > grid.count(&Player::O) {
+                Player::O
+            } else {
+                Player::X
+            };
+            for coordinate in solver.best_moves(&grid, &player) {
+                book.add(&grid, &coordinate, 1);
+            }
+
+            for coordinate in grid.legal_moves() {
+                let mut next_grid = grid.clone();
+                next_grid.set_space(&coordinate, &player).unwrap();
+                positions.push((next_grid, depth + 1));
+            }
+        }
+
+        book
+    }
+
+    // The first `plies` moves of every game in a database, all played from `grid`.
+    pub fn from_games(grid: &Grid, games: &[Standings], plies: usize) -> Self {
+        let mut book = Self::new(grid.width(), grid.height());
+        for standings in games {
+            book.record(grid, standings, plies);
+        }
+        book
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, format!("{}", self))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The number of positions in the book
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    // The book's moves for this board, turned to match it.
+    pub fn moves(&self, grid: &Grid) -> Vec<Entry> {
+        if !self.fits(grid) {
+            return Vec::new();
+        }
+
+        let (key, symmetry) = self.key(grid);
+        self.positions
+            .get(&key)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .map(|entry| Entry {
+                        coordinate: symmetry.invert(&entry.coordinate, self.width),
+                        ..*entry
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Adds to the weight of a move, putting it in the book if it isn't already.
+    pub fn add(&mut self, grid: &Grid, coordinate: &Coordinate, weight: u32) {
+        self.entry(grid, coordinate).weight += weight;
+    }
+
+    // Adds the first `plies` moves of a game played from `grid` to the book, with the
+    // result for each player who made them. Wins add more weight than draws, and losses
+    // add none. Turns placing more than one stone end the opening, and so does the first
+    // capture, since the book's positions don't count captured pairs.
+    pub fn record(&mut self, grid: &Grid, standings: &Standings, plies: usize) {
+        if !self.fits(grid) {
+            return;
+        }
+
+        let winner = standings.get_winner();
+        let mut grid = grid.clone();
+
+        for turn in standings.get_turns().iter().take(plies) {
+            let (player, placements) = (turn.get_player(), turn.get_placements());
+            if placements.len() != 1 {
+                break;
+            }
+
+            let entry = self.entry(&grid, &placements[0]);
+            entry.games += 1;
+            match winner {
+                Some(winner) if winner == player => {
+                    entry.wins += 1;
+                    entry.weight += Self::WIN_WEIGHT;
+                }
+                Some(_) => entry.losses += 1,
+                None => {
+                    entry.draws += 1;
+                    entry.weight += Self::DRAW_WEIGHT;
+                }
+            }
+
+            if turn
+                .get_captures()
+                .iter()
+                .any(|captured| !captured.is_empty())
+            {
+                break;
+            }
+            grid.set_space(&placements[0], &player)
+                .expect("Illegal move!");
+        }
+    }
+
+    // A move from the book for this board, drawn in proportion to the weights.
+    pub fn choose<R: Rng>(&self, grid: &Grid, rng: &mut R) -> Option<Coordinate> {
+        let moves: Vec<Entry> = self
+            .moves(grid)
+            .into_iter()
+            .filter(|entry| entry.weight > 0 && grid.is_legal(&entry.coordinate))
+            .collect();
+
+        moves
+            .choose_weighted(rng, |entry| entry.weight)
+            .ok()
+            .map(|entry| entry.coordinate)
+    }
+
+    fn fits(&self, grid: &Grid) -> bool {
+        grid.width() == self.width && grid.height() == self.height
+    }
+
+    // Only boards that play the same when turned share entries, so others are looked up
+    // as they are.
+    fn key(&self, grid: &Grid) -> (String, Symmetry) {
+        if is_symmetric(grid) {
+            canonical(grid)
+        } else {
+            (key(grid), Symmetry::IDENTITY)
+        }
+    }
+
+    fn entry(&mut self, grid: &Grid, coordinate: &Coordinate) -> &mut Entry {
+        let (key, symmetry) = self.key(grid);
+        let coordinate = symmetry.apply(coordinate, self.width);
+        let entries = self.positions.entry(key).or_default();
+
+        // Moves are kept in board order, row by row.
+        let index =
+            match entries.binary_search_by_key(&order(&coordinate), |e| order(&e.coordinate)) {
+                Ok(index) => index,
+                Err(index) => {
+                    entries.insert(index, Entry::new(coordinate));
+                    index
+                }
+            };
+        &mut entries[index]
+    }
+}
+
+fn order(coordinate: &Coordinate) -> (usize, usize) {
+    (coordinate.y(), coordinate.x())
+}
+
+// One move per line: the canonical board's key, the move on that board, its weight, and
+// its games, wins, draws and losses, eg.
+//
+//     # Opening book
+//     board 3x3
+//     ......... B2 2 1 0 1 0
+impl fmt::Display for Book {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Opening book")?;
+        writeln!(f, "board {}x{}", self.width, self.height)?;
+
+        let mut keys: Vec<&String> = self.positions.keys().collect();
+        keys.sort();
+        for key in keys {
+            for e in self.positions[key].iter() {
+                writeln!(
+                    f,
+                    "{} {} {} {} {} {} {}",
+                    key, e.coordinate, e.weight, e.games, e.wins, e.draws, e.losses,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Book {
+    type Err = FileError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut book: Option<Book> = None;
+
+        for (number, line) in raw.lines().enumerate() {
+            let error =
+                |message: &str| FileError::Parse("opening book", number + 1, message.to_string());
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if let Some(size) = line.strip_prefix("board ") {
+                let (width, height) = match size.trim().split_once('x') {
+                    Some((width, height)) => (width.parse(), height.parse()),
+                    None => return Err(error("expected a board size like 3x3")),
+                };
+                match (width, height) {
+                    (Ok(width), Ok(height)) => book = Some(Book::new(width, height)),
+                    _ => return Err(error("expected a board size like 3x3")),
+                }
+            } else {
+                let book = book
+                    .as_mut()
+                    .ok_or_else(|| error("expected the board size first"))?;
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if parts.len() != 7 {
+                    return Err(error("expected a board, a move, a weight and a record"));
+                }
+
+                let key = parts[0];
+                if key.chars().count() != book.width * book.height
+                    || key.chars().any(|c| !".#XOΔ□".contains(c))
+                {
+                    return Err(error("expected a board like X...O...."));
+                }
+
+                let coordinate: Coordinate = parts[1]
+                    .parse()
+                    .map_err(|_| error("expected a move like B2"))?;
+                if coordinate.x() >= book.width || coordinate.y() >= book.height {
+                    return Err(error("expected a move on the board"));
+                }
+
+                let numbers = parts[2..]
+                    .iter()
+                    .map(|n| n.parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .map_err(|_| error("expected a count"))?;
+
+                book.positions
+                    .entry(key.to_string())
+                    .or_default()
+                    .push(Entry {
+                        coordinate,
+                        weight: numbers[0],
+                        games: numbers[1],
+                        wins: numbers[2],
+                        draws: numbers[3],
+                        losses: numbers[4],
+                    });
+            }
+        }
+
+        let mut book =
+            book.ok_or_else(|| FileError::Parse("opening book", 0, "no board size".to_string()))?;
+        for entries in book.positions.values_mut() {
+            entries.sort_by_key(|e| order(&e.coordinate));
+        }
+        Ok(book)
+    }
+}
+
+// Plays from the book while it has a move for the position, and leaves the rest of the
+// game to another player.
+pub struct BookPlayer {
+    book: Book,
+    player: Box<dyn Playable>,
+    rng: StdRng,
+}
+
+impl BookPlayer {
+    pub fn new(book: Book, player: Box<dyn Playable>) -> Self {
+        Self {
+            book,
+            player,
+            rng: StdRng::seed_from_u64(thread_rng().gen()),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn get_book(&self) -> &Book {
+        &self.book
+    }
+}
+
+impl Playable for BookPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        match self.book.choose(grid, &mut self.rng) {
+            Some(coordinate) => coordinate,
+            None => self.player.play(grid),
+        }
+    }
+
+    fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+        self.player.reply_to_opening(grid)
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player.switch_sides(player);
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.player.reseed(self.rng.gen());
+    }
+}
+
+#[cfg(test)]
+mod test_book {
+    use super::{Book, BookPlayer, FileError};
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::solver::Solver;
+    use crate::{play_game, play_seeded, Playable, WinRule};
+    use std::env;
+
+    struct ScriptedPlayer(Vec<Coordinate>);
+
+    impl Playable for ScriptedPlayer {
+        fn play(&mut self, _grid: &Grid) -> Coordinate {
+            self.0.remove(0)
+        }
+    }
+
+    fn scripted(moves: &[&str]) -> Box<dyn Playable> {
+        Box::new(ScriptedPlayer(
+            moves.iter().map(|m| m.parse().unwrap()).collect(),
+        ))
+    }
+
+    #[test]
+    fn turned_positions_share_moves() {
+        // X..      ...
+        // ...  and ...
+        // ...      ..X
+        let mut book = Book::new(3, 3);
+        let mut grid = Grid::empty();
+        grid.set_space(&"A1".parse().unwrap(), &Player::X).unwrap();
+        book.add(&grid, &"B2".parse().unwrap(), 3);
+        book.add(&grid, &"C1".parse().unwrap(), 1);
+        assert_eq!(1, book.len());
+
+        let mut turned = Grid::empty();
+        turned
+            .set_space(&"C3".parse().unwrap(), &Player::X)
+            .unwrap();
+        let mut moves: Vec<(Coordinate, u32)> = book
+            .moves(&turned)
+            .iter()
+            .map(|entry| (entry.get_coordinate(), entry.get_weight()))
+            .collect();
+        moves.sort_by_key(|(c, _)| (c.y(), c.x()));
+        assert_eq!(2, moves.len());
+        assert_eq!((Coordinate::new(1, 1), 3), moves[0]);
+        assert!(moves[1] == (Coordinate::new(0, 2), 1) || moves[1] == (Coordinate::new(2, 0), 1));
+
+        assert!(book.moves(&Grid::with_size(4, 4, 3)).is_empty());
+
+        // When only the top row wins, A1 and C3 are different openings.
+        let row = ["A1", "B1", "C1"]
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        let board = Grid::with_winning_sets(3, 3, vec![row]);
+        let mut book = Book::new(3, 3);
+        let mut grid = board.clone();
+        grid.set_space(&"A1".parse().unwrap(), &Player::X).unwrap();
+        book.add(&grid, &"B1".parse().unwrap(), 1);
+
+        let mut turned = board;
+        turned
+            .set_space(&"C3".parse().unwrap(), &Player::X)
+            .unwrap();
+        assert!(book.moves(&turned).is_empty());
+        assert_eq!(1, book.moves(&grid).len());
+    }
+
+    #[test]
+    fn solver_book_plays_perfectly() {
+        let book = Book::from_solver(&Grid::empty(), 9, &mut Solver::new());
+        let openings: Vec<Coordinate> = book
+            .moves(&Grid::empty())
+            .iter()
+            .map(|entry| entry.get_coordinate())
+            .collect();
+        assert_eq!(9, openings.len());
+
+        for seed in 0..20 {
+            let player = BookPlayer::new(book.clone(), Box::new(RandoPlayer::new()));
+            let grid = play_seeded(Box::new(player), Box::new(RandoPlayer::new()), seed);
+            assert_ne!(Some(Player::O), grid.get_winner());
+
+            let player = BookPlayer::new(book.clone(), Box::new(RandoPlayer::new()));
+            let grid = play_seeded(Box::new(RandoPlayer::new()), Box::new(player), seed);
+            assert_ne!(Some(Player::X), grid.get_winner());
+        }
+    }
+
+    #[test]
+    fn records_games() {
+        let game = |x: &[&str], o: &[&str]| {
+            play_game(
+                Grid::empty(),
+                vec![(Player::X, scripted(x)), (Player::O, scripted(o))],
+                WinRule::FirstToLine,
+            )
+        };
+
+        let games = vec![
+            game(&["B2", "A1", "C3"], &["B1", "A3"]),
+            game(&["B2", "A1", "C1"], &["A3", "B3", "C3"]),
+        ];
+        let book = Book::from_games(&Grid::empty(), &games, 2);
+
+        // Both games opened in the centre; X won the first and O the second.
+        let openings = book.moves(&Grid::empty());
+        assert_eq!(1, openings.len());
+        let centre = openings[0];
+        assert_eq!(Coordinate::new(1, 1), centre.get_coordinate());
+        assert_eq!(
+            (2, 1, 0, 1, 2),
+            (
+                centre.get_games(),
+                centre.get_wins(),
+                centre.get_draws(),
+                centre.get_losses(),
+                centre.get_weight(),
+            ),
+        );
+
+        // O answered with an edge and then a corner.
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(1, 1), &Player::X).unwrap();
+        assert_eq!(2, book.moves(&grid).len());
+        assert_eq!(2, book.len());
+    }
+
+    #[test]
+    fn stops_at_captures() {
+        // X's D1 captures O's pair on B1 and C1, and O plays back into B1.
+        let standings = play_game(
+            Grid::with_size(5, 1, 4).with_captures(),
+            vec![
+                (Player::X, scripted(&["A1", "E1", "D1", "C1"])),
+                (Player::O, scripted(&["B1", "C1", "B1"])),
+            ],
+            WinRule::FirstToLine,
+        );
+        let book = Book::from_games(&Grid::with_size(5, 1, 4), &[standings], 9);
+
+        // The capturing move is the last one recorded.
+        assert_eq!(5, book.len());
+        let mut grid = Grid::with_size(5, 1, 4);
+        for (raw, player) in [("A1", Player::X), ("B1", Player::O), ("E1", Player::X)].iter() {
+            grid.set_space(&raw.parse().unwrap(), player).unwrap();
+        }
+        assert_eq!(1, book.moves(&grid).len());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let book = Book::from_solver(&Grid::empty(), 2, &mut Solver::new());
+        let path = env::temp_dir().join(format!("book-{}.txt", std::process::id()));
+        book.save(&path).unwrap();
+        let loaded = Book::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(book, loaded.unwrap());
+
+        match "board 3x3\n......... B2 1 0 0 0".parse::<Book>() {
+            Err(FileError::Parse("opening book", 2, _)) => {}
+            other => panic!("expected an error on line 2, got {:?}", other),
+        }
+        match "......... B2 1 0 0 0 0".parse::<Book>() {
+            Err(FileError::Parse("opening book", 1, _)) => {}
+            other => panic!("expected an error on line 1, got {:?}", other),
+        }
+    }
+}
@@ -1,162 +1,460 @@
-use super::{Coordinate, Grid, Playable, Player};
+use super::game::Space;
+use super::solver::Solver;
+use super::strategy::{Context, Pipeline};
+use super::{Coordinate, Grid, Playable, Player, Reply};
 use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::cmp::Reverse;
+use std::fmt;
 
 pub struct ComputerPlayer {
     player: Player,
-    rng: ThreadRng,
+    rng: StdRng,
     verbosity: u8,
+    difficulty: Difficulty,
+    personality: Personality,
+    solver: Solver,
+    pipeline: Pipeline,
+    reporter: Option<Reporter>,
+}
+
+// Somewhere to send the rationale for each move
+type Reporter = Box<dyn FnMut(&Rationale)>;
+
+// How well the computer plays. Weaker levels stop partway down its pipeline of strategies
+// and sometimes move at random instead; `Perfect` only considers moves that keep
+// the best result, and so never loses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Difficulty {
+    // Takes wins, but half its moves are random
+    Beginner,
+    // Also blocks, and a quarter of its moves are random
+    Easy,
+    // Also forks and blocks forks, and one move in ten is random
+    Medium,
+    // Follows every rule, without mistakes
+    Hard,
+    // Searches the whole game, so it's only practical on small two-player boards
+    Perfect,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 5] = [
+        Difficulty::Beginner,
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Perfect,
+    ];
+
+    // The chance of playing a random move instead of following the rules
+    fn mistake_probability(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 0.5,
+            Difficulty::Easy => 0.25,
+            Difficulty::Medium => 0.1,
+            Difficulty::Hard | Difficulty::Perfect => 0.0,
+        }
+    }
+
+    // How many of the strategies are followed, in order
+    fn steps(&self) -> usize {
+        match self {
+            Difficulty::Beginner => 1,
+            Difficulty::Easy => 2,
+            Difficulty::Medium => 4,
+            Difficulty::Hard | Difficulty::Perfect => usize::MAX,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Beginner => write!(f, "beginner"),
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+            Difficulty::Perfect => write!(f, "perfect"),
+        }
+    }
+}
+
+// How the computer likes to play. A personality never overrules the rules, and never
+// touches a random move or a mistake: it only breaks ties, so whenever the rule that fires
+// would accept more than one move, or at the `Perfect` level when no rule fires and any of
+// the solver's best moves will do, it picks the one with the most character. A level
+// plays about as well with any personality, and the trickster's odd moves stay sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Personality {
+    // No preferences, just a random pick
+    Plain,
+    // Prefers moves that threaten to win next turn, then moves on open lines
+    Aggressive,
+    // Prefers moves that get in the way of the opponents' best lines
+    Defensive,
+    // Prefers the spaces on the fewest lines, eg. edges on a 3x3 board
+    Trickster,
+    // Prefers the space opposite, through the centre, to one an opponent holds
+    Mirror,
+}
+
+impl Personality {
+    pub const ALL: [Personality; 5] = [
+        Personality::Plain,
+        Personality::Aggressive,
+        Personality::Defensive,
+        Personality::Trickster,
+        Personality::Mirror,
+    ];
+
+    // How much the personality likes a move. Higher is better, and ties are left in the
+    // order they were in.
+    fn preference(&self, grid: &Grid, player: &Player, coordinate: &Coordinate) -> usize {
+        let owner = |space: &Space| space.get_player();
+
+        match self {
+            Personality::Plain => 0,
+            Personality::Aggressive => grid
+                .lines_through(coordinate)
+                .map(|line| line.get_spaces())
+                .filter(|spaces| {
+                    spaces
+                        .iter()
+                        .all(|s| *s == Space::Empty || owner(s) == Some(*player))
+                })
+                .map(|spaces| {
+                    let stones = spaces.iter().filter(|s| **s != Space::Empty).count();
+                    if stones + 2 == spaces.len() {
+                        // Another stone here leaves one space to go
+                        spaces.len() * spaces.len()
+                    } else {
+                        1 + stones
+                    }
+                })
+                .sum(),
+            Personality::Defensive => grid
+                .lines_through(coordinate)
+                .map(|line| line.get_spaces())
+                .filter(|spaces| spaces.iter().all(|s| owner(s) != Some(*player)))
+                .map(|spaces| {
+                    let stones = spaces.iter().filter(|s| owner(s).is_some()).count();
+                    stones * stones
+                })
+                .sum(),
+            Personality::Trickster => {
+                let lines = grid.lines_through(coordinate).count();
+                grid.lines().count().saturating_sub(lines)
+            }
+            Personality::Mirror => {
+                let opposite = Coordinate::new(
+                    grid.width() - 1 - coordinate.x(),
+                    grid.height() - 1 - coordinate.y(),
+                );
+                match owner(&grid.get_space(&opposite)) {
+                    Some(other) if other != *player => 1,
+                    _ => 0,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Personality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Personality::Plain => write!(f, "plain"),
+            Personality::Aggressive => write!(f, "aggressive"),
+            Personality::Defensive => write!(f, "defensive"),
+            Personality::Trickster => write!(f, "trickster"),
+            Personality::Mirror => write!(f, "mirror"),
+        }
+    }
+}
+
+// Why the computer played a move: the rule that picked it and the ones asked before that
+// had nothing to suggest, the wins on offer to each player, the other moves it could
+// have played and, at the `Perfect` level, the result it expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rationale {
+    player: Player,
+    coordinate: Coordinate,
+    rule: Option<&'static str>,
+    passed: Vec<&'static str>,
+    mistake: bool,
+    threats: Vec<(Player, Coordinate)>,
+    alternatives: Vec<Coordinate>,
+    evaluation: Option<i8>,
+}
+
+impl Rationale {
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_coordinate(&self) -> Coordinate {
+        self.coordinate
+    }
+
+    // The strategy that suggested the move, or `None` if it was played at random
+    pub fn get_rule(&self) -> Option<&'static str> {
+        self.rule
+    }
+
+    // The strategies asked first, in order, that had no suggestion
+    pub fn get_passed(&self) -> &[&'static str] {
+        &self.passed
+    }
+
+    // Whether a weaker level skipped its strategies this move
+    pub fn is_mistake(&self) -> bool {
+        self.mistake
+    }
+
+    // Every space where a player could complete a line on their next move
+    pub fn get_threats(&self) -> &[(Player, Coordinate)] {
+        &self.threats
+    }
+
+    pub fn get_alternatives(&self) -> &[Coordinate] {
+        &self.alternatives
+    }
+
+    // The solver's result for the mover after this move: 1 for a win, 0 for a draw and
+    // -1 for a loss. Only the `Perfect` level solves the game.
+    pub fn get_evaluation(&self) -> Option<i8> {
+        self.evaluation
+    }
+}
+
+impl fmt::Display for Rationale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (player, coordinate) in self.threats.iter() {
+            writeln!(f, "{} can win by playing {}", player, coordinate)?;
+        }
+
+        if self.mistake {
+            writeln!(f, "{} isn't paying attention", self.player)?;
+        }
+        for rule in self.passed.iter() {
+            writeln!(f, "{} has no {} move", self.player, rule)?;
+        }
+        match self.rule {
+            Some(rule) => writeln!(f, "{} follows the {} rule", self.player, rule)?,
+            None => writeln!(f, "{} picks a move at random", self.player)?,
+        }
+
+        if !self.alternatives.is_empty() {
+            let alternatives: Vec<String> =
+                self.alternatives.iter().map(|c| c.to_string()).collect();
+            writeln!(f, "{} passes over {}", self.player, alternatives.join(", "))?;
+        }
+        match self.evaluation {
+            Some(1) => writeln!(f, "{} expects to win", self.player)?,
+            Some(0) => writeln!(f, "{} expects a draw", self.player)?,
+            Some(_) => writeln!(f, "{} expects to lose", self.player)?,
+            None => {}
+        }
+
+        write!(f, "{} chooses {}", self.player, self.coordinate)
+    }
 }
 
 impl ComputerPlayer {
     pub fn new(player: Player) -> Self {
         Self {
             player,
-            rng: thread_rng(),
+            rng: StdRng::seed_from_u64(thread_rng().gen()),
             verbosity: 1,
+            difficulty: Difficulty::Hard,
+            personality: Personality::Plain,
+            solver: Solver::new(),
+            pipeline: Pipeline::classic(),
+            reporter: None,
         }
     }
 
     pub fn new_verbose(player: Player) -> Self {
         Self {
-            player,
-            rng: thread_rng(),
             verbosity: 2,
+            ..Self::new(player)
         }
     }
 
     pub fn new_silent(player: Player) -> Self {
         Self {
-            player,
-            rng: thread_rng(),
             verbosity: 0,
+            ..Self::new(player)
         }
     }
 
-    fn try_move(&self, grid: &Grid, coordinate: &Coordinate, player: &Player) -> Grid {
-        let mut grid = grid.clone();
-        grid.set_space(coordinate, player).ok(); // This is okay.
-        grid
-    }
-
-    fn check_move(
-        &self,
-        grid: &Grid,
-        legal_moves: &Vec<Coordinate>,
-        player: &Player,
-    ) -> Option<Coordinate> {
-        for coordinate in legal_moves {
-            let next_grid = self.try_move(grid, coordinate, player);
-            for next_coordinate in legal_moves {
-                if next_coordinate != coordinate {
-                    if self
-                        .try_move(&next_grid, next_coordinate, player)
-                        .get_winner()
-                        .is_some()
-                    {
-                        if self.verbosity >= 2 {
-                            println!("{} can set up a check by playing {}", player, coordinate);
-                        }
-                        return Some(*coordinate);
-                    }
-                }
-            }
-        }
-        None
-    }
-
-    fn forking_move(
-        &self,
-        grid: &Grid,
-        legal_moves: &Vec<Coordinate>,
-        player: &Player,
-    ) -> Option<Coordinate> {
-        for coordinate in legal_moves {
-            let mut fork_count = 0;
-            let next_grid = self.try_move(grid, coordinate, player);
-            for next_coordinate in legal_moves {
-                if next_coordinate != coordinate {
-                    if self
-                        .try_move(&next_grid, next_coordinate, player)
-                        .get_winner()
-                        .is_some()
-                    {
-                        fork_count += 1;
-                    }
-                }
-            }
-            if fork_count > 1 {
-                if self.verbosity >= 2 {
-                    println!("{} can set up a fork by playing {}", player, coordinate);
+    // Makes the player's random choices repeatable.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn with_difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    pub fn with_personality(mut self, personality: Personality) -> Self {
+        self.personality = personality;
+        self
+    }
+
+    // Plays by a custom pipeline of strategies instead of the usual chain, falling back
+    // on a random move when none of them has a suggestion.
+    pub fn with_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    // Hands the rationale for every move to `reporter`, eg. to log it or show it in a UI.
+    // It's called even when the player is silent.
+    pub fn with_reporter<F: FnMut(&Rationale) + 'static>(mut self, reporter: F) -> Self {
+        self.reporter = Some(Box::new(reporter));
+        self
+    }
+
+    // Picks a move without playing it or printing anything, and explains the choice.
+    pub fn decide(&mut self, grid: &Grid) -> Rationale {
+        let player = self.player;
+        let (mut legal_moves, evaluation) = if self.difficulty == Difficulty::Perfect {
+            let best_moves = self.solver.best_moves(grid, &player);
+            (best_moves, Some(self.solver.value(grid, &player)))
+        } else {
+            (grid.legal_moves(), None)
+        };
+
+        legal_moves.shuffle(&mut self.rng);
+
+        // Strategies take the first move they accept, so the personality's favourites go
+        // first. Random moves keep the shuffled order.
+        let personality = self.personality;
+        let mut preferred = legal_moves.clone();
+        preferred.sort_by_key(|c| Reverse(personality.preference(grid, &player, c)));
+
+        let mistake = self.rng.gen_bool(self.difficulty.mistake_probability());
+        let steps = if mistake { 0 } else { self.difficulty.steps() };
+
+        let context = Context::new(grid, player, &preferred);
+        let mut passed = Vec::new();
+        let mut choice = None;
+        for strategy in self.pipeline.get_strategies().iter().take(steps) {
+            match strategy.choose(&context) {
+                Some(coordinate) => {
+                    choice = Some((coordinate, strategy.name()));
+                    break;
                 }
-                return Some(*coordinate);
+                None => passed.push(strategy.name()),
             }
         }
-        None
-    }
-
-    fn winning_move(
-        &self,
-        grid: &Grid,
-        legal_moves: &Vec<Coordinate>,
-        player: &Player,
-    ) -> Option<Coordinate> {
-        for coordinate in legal_moves {
-            if self
-                .try_move(grid, coordinate, player)
-                .get_winner()
-                .is_some()
-            {
-                if self.verbosity >= 2 {
-                    println!("{} can win by playing {}", player, coordinate);
-                }
-                return Some(*coordinate);
+
+        let (coordinate, rule) = match choice {
+            Some((coordinate, rule)) => (coordinate, Some(rule)),
+            // Every one of the solver's best moves will do, so pick one with character.
+            None if self.difficulty == Difficulty::Perfect => {
+                (*preferred.first().expect("No legal moves!"), None)
             }
+            // Just make a random move
+            None => (*legal_moves.first().expect("No legal moves!"), None),
+        };
+
+        let threats = std::iter::once(player)
+            .chain(context.get_opponents().iter().copied())
+            .flat_map(|p| {
+                grid.legal_moves()
+                    .into_iter()
+                    .filter(move |c| grid.wins_with(c, &p))
+                    .map(move |c| (p, c))
+            })
+            .collect();
+
+        let mut alternatives: Vec<Coordinate> = legal_moves
+            .iter()
+            .copied()
+            .filter(|c| *c != coordinate)
+            .collect();
+        alternatives.sort_by_key(|c| (c.y(), c.x()));
+
+        Rationale {
+            player,
+            coordinate,
+            rule,
+            passed,
+            mistake,
+            threats,
+            alternatives,
+            evaluation,
         }
-        None
     }
 }
 
 impl Playable for ComputerPlayer {
     fn play(&mut self, grid: &Grid) -> Coordinate {
-        let mut legal_moves = Vec::with_capacity(9);
-        for x in 0..=2 {
-            for y in 0..=2 {
-                let coordinate = Coordinate::new(x, y);
-                if grid.is_legal(&coordinate) {
-                    legal_moves.push(coordinate);
-                }
-            }
+        let rationale = self.decide(grid);
+
+        if self.verbosity >= 2 {
+            println!("{}", rationale);
+            println!();
+        } else if self.verbosity >= 1 {
+            println!("{} chooses {}", self.player, rationale.coordinate);
+            println!();
         }
 
-        legal_moves.shuffle(&mut self.rng);
+        if let Some(reporter) = self.reporter.as_mut() {
+            reporter(&rationale);
+        }
 
-        let coordinate = None
-            // Can I make a winning move?
-            .or_else(|| self.winning_move(&grid, &legal_moves, &self.player))
-            // Can my opponent make a winning move?
-            .or_else(|| self.winning_move(&grid, &legal_moves, &self.player.turn()))
-            // Can I be cunning and fork the other player?
-            .or_else(|| self.forking_move(&grid, &legal_moves, &self.player))
-            // Can the other player make a legal move that will cause me to be forked?
-            .or_else(|| self.forking_move(&grid, &legal_moves, &self.player.turn()))
-            // Can I at least make a move that will put the other player in check?
-            .or_else(|| self.check_move(&grid, &legal_moves, &self.player))
-            // Just make a random move
-            .or_else(|| legal_moves.first().cloned())
-            .expect("No legal moves!");
+        rationale.coordinate
+    }
+
+    fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+        // Take over the opening stone if it sits on more lines than the average space,
+        // eg. the centre or a corner on a 3x3 board.
+        let opening = grid
+            .coordinates()
+            .find(|c| grid.get_space(c).get_player() == Some(Player::X));
+        let spaces = grid
+            .coordinates()
+            .filter(|c| grid.get_space(c) != Space::Blocked)
+            .count();
+        let memberships: usize = grid.lines().map(|l| l.get_coordinates().len()).sum();
 
-        if self.verbosity >= 1 {
-            println!("{} chooses {}", self.player, coordinate);
-            println!("");
+        match opening {
+            Some(opening) if grid.lines_through(&opening).count() * spaces > memberships => {
+                if self.verbosity >= 1 {
+                    println!("{} swaps sides to take {}", self.player, opening);
+                    println!();
+                }
+                Reply::Swap
+            }
+            _ => Reply::Play(self.play(grid)),
         }
+    }
 
-        coordinate
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{ComputerPlayer, Coordinate, Grid, Playable, Player};
+    use super::{
+        ComputerPlayer, Coordinate, Difficulty, Grid, Personality, Playable, Player, Reply,
+    };
+    use crate::game::Space;
+    use crate::rando::RandoPlayer;
+    use crate::strategy::Pipeline;
+    use crate::{play, play_seeded};
+    use std::cell::RefCell;
+    use std::rc::Rc;
 
     #[test]
     fn takes_winning_move() {
@@ -282,26 +580,289 @@ mod test {
 
     #[test]
     fn moves_at_random() {
-        let mut player_x = ComputerPlayer::new(Player::X);
-        let mut player_o = ComputerPlayer::new(Player::O);
+        let game = |seed| {
+            let mut player_x = ComputerPlayer::new_silent(Player::X).with_seed(seed);
+            let mut player_o = ComputerPlayer::new_silent(Player::O).with_seed(seed + 1);
+            let mut grid = Grid::empty();
 
-        let mut grids = [Grid::empty(), Grid::empty()];
-
-        for i in 0..=1 {
-            while grids[i].get_winner() == None && grids[i].has_legal_moves() {
-                grids[i]
-                    .set_space(&player_x.play(&grids[i]), &Player::X)
-                    .unwrap();
-                if grids[i].get_winner().is_some() || !grids[i].has_legal_moves() {
+            while grid.get_winner().is_none() && grid.has_legal_moves() {
+                grid.set_space(&player_x.play(&grid), &Player::X).unwrap();
+                if grid.get_winner().is_some() || !grid.has_legal_moves() {
                     break;
                 }
-                grids[i]
-                    .set_space(&player_o.play(&grids[i]), &Player::O)
-                    .unwrap();
+                grid.set_space(&player_o.play(&grid), &Player::O).unwrap();
+            }
+
+            grid
+        };
+
+        // The same seeds replay the same game, and different ones break ties differently.
+        assert_eq!(game(1), game(1));
+        assert_ne!(game(1), game(3));
+    }
+
+    #[test]
+    fn swaps_strong_openings() {
+        for (opening, swaps) in [("B2", true), ("A1", true), ("B1", false)].iter() {
+            let mut player = ComputerPlayer::new_silent(Player::O);
+            let mut grid = Grid::empty();
+            grid.set_space(&opening.parse().unwrap(), &Player::X)
+                .unwrap();
+            assert_eq!(
+                *swaps,
+                player.reply_to_opening(&grid) == Reply::Swap,
+                "{}",
+                opening,
+            );
+        }
+    }
+
+    #[test]
+    fn plays_new_side_after_swap() {
+        // XX*
+        // OO.
+        // ...
+        let mut player = ComputerPlayer::new(Player::O);
+        player.switch_sides(Player::X);
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+        assert_eq!(Coordinate::new(2, 0), player.play(&grid));
+    }
+
+    #[test]
+    fn blocks_any_opponent() {
+        // X..Δ
+        // O*..
+        // .Δ..
+        // ..O.
+        let mut player = ComputerPlayer::new(Player::X);
+        let mut grid = Grid::with_size(4, 4, 3);
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(3, 0), &Player::Delta)
+            .unwrap();
+        grid.set_space(&Coordinate::new(2, 3), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 2), &Player::Delta)
+            .unwrap();
+        assert_eq!(Coordinate::new(2, 1), player.play(&grid));
+    }
+
+    #[test]
+    fn weaker_levels_make_mistakes() {
+        // OO*
+        // X..
+        // ..X
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::X).unwrap();
+
+        let blocks = |difficulty: Difficulty| {
+            let mut player = ComputerPlayer::new_silent(Player::X).with_difficulty(difficulty);
+            (0..200)
+                .filter(|_| player.play(&grid) == Coordinate::new(2, 0))
+                .count()
+        };
+
+        // A beginner doesn't look for blocks at all, and an easy player sometimes slips.
+        assert!(blocks(Difficulty::Beginner) < 200);
+        assert!(blocks(Difficulty::Easy) < 200);
+        assert_eq!(200, blocks(Difficulty::Hard));
+    }
+
+    #[test]
+    fn perfect_play_avoids_losing_replies() {
+        // X..
+        // ...
+        // ...   Only the centre holds the draw, and no rule in the chain says so.
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+
+        let mut player = ComputerPlayer::new_silent(Player::O).with_difficulty(Difficulty::Perfect);
+        for _ in 0..10 {
+            assert_eq!(Coordinate::new(1, 1), player.play(&grid));
+        }
+    }
+
+    #[test]
+    fn custom_pipeline() {
+        let pipeline = "win, block, center, opposite-corner".parse().unwrap();
+        let mut player = ComputerPlayer::new_silent(Player::O).with_pipeline(pipeline);
+
+        // .X.
+        // ...
+        // ...
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+        assert_eq!(Coordinate::new(1, 1), player.play(&grid));
+
+        // X..
+        // .X.
+        // ..*
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::X).unwrap();
+        assert_eq!(Coordinate::new(2, 2), player.play(&grid));
+
+        let mut player = ComputerPlayer::new_silent(Player::X).with_pipeline(Pipeline::new());
+        assert!(Grid::empty().is_legal(&player.play(&Grid::empty())));
+    }
+
+    #[test]
+    fn explains_moves() {
+        // O..
+        // .*.
+        // ..O
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+
+        let rationale = ComputerPlayer::new_silent(Player::X).decide(&grid);
+        assert_eq!(Coordinate::new(1, 1), rationale.get_coordinate());
+        assert_eq!(Some("block"), rationale.get_rule());
+        assert_eq!(&["win"], rationale.get_passed());
+        assert!(!rationale.is_mistake());
+        assert_eq!(
+            &[(Player::O, Coordinate::new(1, 1))],
+            rationale.get_threats(),
+        );
+        assert_eq!(5, rationale.get_alternatives().len());
+        assert_eq!(None, rationale.get_evaluation());
+        assert!(rationale
+            .to_string()
+            .ends_with("X follows the block rule\nX passes over C1, A2, C2, A3, B3\nX chooses B2"));
+
+        let rationale = ComputerPlayer::new_silent(Player::X)
+            .with_difficulty(Difficulty::Perfect)
+            .decide(&Grid::empty());
+        assert_eq!(Some(0), rationale.get_evaluation());
+    }
+
+    #[test]
+    fn reports_rationales() {
+        let rationales = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&rationales);
+        let player = ComputerPlayer::new_silent(Player::X)
+            .with_reporter(move |rationale| log.borrow_mut().push(rationale.clone()));
+
+        let grid = play(
+            Box::new(player),
+            Box::new(ComputerPlayer::new_silent(Player::O)),
+        );
+        let rationales = rationales.borrow();
+        assert_eq!(grid.count(&Player::X), rationales.len());
+        assert!(rationales.iter().all(|r| r.get_player() == Player::X
+            && grid.get_space(&r.get_coordinate()) == Space::new(Some(&Player::X))));
+    }
+
+    #[test]
+    fn personalities_break_ties() {
+        // With no rules, a perfect player may take any of the solver's best moves.
+        let player = |personality| {
+            ComputerPlayer::new_silent(Player::X)
+                .with_difficulty(Difficulty::Perfect)
+                .with_pipeline(Pipeline::new())
+                .with_personality(personality)
+        };
+        let grid = |stones: &[(&str, Player)]| {
+            let mut grid = Grid::empty();
+            for (coordinate, p) in stones.iter() {
+                grid.set_space(&coordinate.parse().unwrap(), p).unwrap();
             }
+            grid
+        };
+
+        // X..
+        // .O.
+        // ...
+        let threats: Vec<Coordinate> = ["B1", "C1", "A2", "A3"]
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        let grid_1 = grid(&[("A1", Player::X), ("B2", Player::O)]);
+        assert!(threats.contains(&player(Personality::Aggressive).play(&grid_1)));
+
+        // O..
+        // OX.
+        // *..
+        let grid_2 = grid(&[("A1", Player::O), ("A2", Player::O), ("B2", Player::X)]);
+        assert_eq!(
+            "A3".parse::<Coordinate>().unwrap(),
+            player(Personality::Defensive).play(&grid_2),
+        );
+
+        let edges: Vec<Coordinate> = ["B1", "A2", "C2", "B3"]
+            .iter()
+            .map(|c| c.parse().unwrap())
+            .collect();
+        assert!(edges.contains(&player(Personality::Trickster).play(&Grid::empty())));
+
+        // O..
+        // .X.
+        // ..*
+        let grid_3 = grid(&[("A1", Player::O), ("B2", Player::X)]);
+        assert_eq!(
+            "C3".parse::<Coordinate>().unwrap(),
+            player(Personality::Mirror).play(&grid_3),
+        );
+    }
+
+    #[test]
+    fn personalities_keep_random_moves() {
+        // X..
+        // ...
+        // ...   No rule fires at the `Hard` level, so the reply is random, and the same
+        //       seed gives the same reply whatever the personality.
+        let mut grid = Grid::empty();
+        grid.set_space(&"A1".parse().unwrap(), &Player::X).unwrap();
+        let reply = |personality, seed| {
+            ComputerPlayer::new_silent(Player::O)
+                .with_personality(personality)
+                .with_seed(seed)
+                .play(&grid)
+        };
+        for seed in 0..10 {
+            assert_eq!(
+                reply(Personality::Plain, seed),
+                reply(Personality::Trickster, seed),
+            );
         }
 
-        // Each game should have a different outcome.
-        assert_ne!(grids[0], grids[1]);
+        // A weak player stays weak: every personality loses about as often as `Plain`.
+        let losses = |personality| {
+            (0..200)
+                .filter(|seed| {
+                    let computer = ComputerPlayer::new_silent(Player::O)
+                        .with_difficulty(Difficulty::Beginner)
+                        .with_personality(personality);
+                    let grid = play_seeded(Box::new(RandoPlayer::new()), Box::new(computer), *seed);
+                    grid.get_winner() == Some(Player::X)
+                })
+                .count()
+        };
+        let plain = losses(Personality::Plain);
+        assert!(plain > 50);
+        for personality in Personality::ALL.iter() {
+            let difference = (losses(*personality) as isize - plain as isize).abs();
+            assert!(difference < 20, "{}", personality);
+        }
+    }
+
+    #[test]
+    fn perfect_personalities_never_lose() {
+        for personality in Personality::ALL.iter() {
+            for seed in 0..10 {
+                let computer = ComputerPlayer::new_silent(Player::O)
+                    .with_difficulty(Difficulty::Perfect)
+                    .with_personality(*personality);
+                let grid = play_seeded(Box::new(RandoPlayer::new()), Box::new(computer), seed);
+                assert_ne!(Some(Player::X), grid.get_winner(), "{}", personality);
+            }
+        }
     }
 }
@@ -0,0 +1,29 @@
+use std::fmt;
+use std::io;
+
+// What can go wrong loading one of the crate's text files.
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    // The kind of file, eg. `hypergraph`, and the line number the problem was found on, or
+    // 0 for the file as a whole
+    Parse(&'static str, usize, String),
+}
+
+impl From<io::Error> for FileError {
+    fn from(error: io::Error) -> Self {
+        FileError::Io(error)
+    }
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "Couldn't read file: {}", e),
+            FileError::Parse(kind, 0, message) => write!(f, "Invalid {}: {}", kind, message),
+            FileError::Parse(kind, line, message) => {
+                write!(f, "Invalid {} on line {}: {}", kind, line, message)
+            }
+        }
+    }
+}
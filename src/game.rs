@@ -1,31 +1,133 @@
 use std::fmt;
+use std::hash::{Hash, Hasher};
 use std::iter;
+use std::rc::Rc;
 use std::str::FromStr;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Grid {
-    spaces: [[Space; 3]; 3],
+    // Row by row, top to bottom
+    spaces: Vec<Space>,
+    lines: Rc<Lines>,
+    // Pairs captured by each player, in `Player::ALL` order, when the capture rule is on
+    captures: Option<[usize; 4]>,
 }
 
 impl Grid {
+    pub const CAPTURES_TO_WIN: usize = 5;
+
     pub fn new(spaces: [[Space; 3]; 3]) -> Grid {
-        Grid { spaces }
+        let mut grid = Grid::with_size(3, 3, 3);
+        for (y, row) in spaces.iter().enumerate() {
+            grid.spaces[y * 3..(y + 1) * 3].copy_from_slice(row);
+        }
+        grid
     }
 
     pub fn empty() -> Grid {
-        Grid::new([
-            [Space::Empty, Space::Empty, Space::Empty],
-            [Space::Empty, Space::Empty, Space::Empty],
-            [Space::Empty, Space::Empty, Space::Empty],
-        ])
+        Grid::with_size(3, 3, 3)
+    }
+
+    pub fn with_size(width: usize, height: usize, line_length: usize) -> Grid {
+        assert!(
+            line_length > 0 && line_length <= width.max(height),
+            "Lines must be between 1 and {} spaces long on a {}x{} board",
+            width.max(height),
+            width,
+            height,
+        );
+        Grid::with_lines(width, height, Lines::new(width, height, line_length))
+    }
+
+    // A board won by claiming any one of the given sets of spaces, rather than by a
+    // straight line. The sets can be any shape or size, and may overlap.
+    pub fn with_winning_sets(
+        width: usize,
+        height: usize,
+        winning_sets: Vec<Vec<Coordinate>>,
+    ) -> Grid {
+        assert!(
+            winning_sets
+                .iter()
+                .flatten()
+                .all(|c| c.0 < width && c.1 < height),
+            "Winning sets must fit on the board",
+        );
+        assert!(
+            winning_sets.iter().all(|set| !set.is_empty()),
+            "Winning sets can't be empty",
+        );
+        Grid::with_lines(
+            width,
+            height,
+            Lines::from_profiles(width, height, winning_sets),
+        )
+    }
+
+    // A board won by filling any of the given shapes, wherever on the board it falls.
+    pub fn with_shapes(width: usize, height: usize, shapes: &[Shape]) -> Grid {
+        Grid::with_lines(width, height, Lines::from_shapes(width, height, shapes))
+    }
+
+    fn with_lines(width: usize, height: usize, lines: Lines) -> Grid {
+        assert!(
+            width > 0 && width <= 26,
+            "Grid must be 1 to 26 columns wide"
+        );
+        assert!(height > 0, "Grid must have at least one row");
+
+        Grid {
+            spaces: vec![Space::Empty; width * height],
+            lines: Rc::new(lines),
+            captures: None,
+        }
+    }
+
+    // Turns on the Pente capture rule: bracketing exactly two enemy stones between the
+    // stone just played and another of your own removes them, and capturing
+    // `Grid::CAPTURES_TO_WIN` pairs wins the game.
+    pub fn with_captures(mut self) -> Grid {
+        self.captures = Some([0; 4]);
+        self
+    }
+
+    pub fn has_captures(&self) -> bool {
+        self.captures.is_some()
+    }
+
+    // Whether two grids are copies of the same board, sharing its winning sets.
+    pub fn shares_lines(&self, other: &Grid) -> bool {
+        Rc::ptr_eq(&self.lines, &other.lines)
+    }
+
+    // How many pairs the player has captured so far.
+    pub fn captured(&self, player: &Player) -> usize {
+        self.captures.map_or(0, |captures| captures[player.index()])
+    }
+
+    pub fn width(&self) -> usize {
+        self.lines.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.spaces.len() / self.width()
+    }
+
+    pub fn contains(&self, coordinate: &Coordinate) -> bool {
+        coordinate.0 < self.width() && coordinate.1 < self.height()
+    }
+
+    pub fn coordinates(&self) -> impl Iterator<Item = Coordinate> {
+        let height = self.height();
+        (0..self.width()).flat_map(move |x| (0..height).map(move |y| Coordinate(x, y)))
     }
 
     pub fn get_space(&self, coordinate: &Coordinate) -> Space {
-        self.spaces[coordinate.1][coordinate.0]
+        self.spaces[self.index(coordinate)]
     }
 
     pub fn is_legal(&self, coordinate: &Coordinate) -> bool {
-        self.get_space(coordinate).get_player() == None
+        self.contains(coordinate) && self.get_space(coordinate) == Space::Empty
     }
 
     pub fn try_legal(&self, coordinate: &Coordinate) -> Result<(), IllegalMove> {
@@ -36,17 +138,116 @@ impl Grid {
         }
     }
 
+    pub fn legal_moves(&self) -> Vec<Coordinate> {
+        self.coordinates().filter(|c| self.is_legal(c)).collect()
+    }
+
     pub fn set_space(
         &mut self,
         coordinate: &Coordinate,
         player: &Player,
     ) -> Result<(), IllegalMove> {
-        self.try_legal(coordinate)
-            .map(|_| self.spaces[coordinate.1][coordinate.0] = Space::new(Some(player)))
+        self.try_legal(coordinate)?;
+
+        let captured = self.captures_with(coordinate, player);
+        let index = self.index(coordinate);
+        self.spaces[index] = Space::new(Some(player));
+
+        if let Some(captures) = self.captures.as_mut() {
+            captures[player.index()] += captured.len() / 2;
+        }
+        for coordinate in captured {
+            let index = self.index(&coordinate);
+            self.spaces[index] = Space::Empty;
+        }
+
+        Ok(())
+    }
+
+    // The stones playing the given (empty) space would capture, pair by pair. Always empty
+    // unless the capture rule is on.
+    pub fn captures_with(&self, coordinate: &Coordinate, player: &Player) -> Vec<Coordinate> {
+        let mut captured = Vec::new();
+        if !self.has_captures() {
+            return captured;
+        }
+
+        let own = Space::new(Some(player));
+        let step = |c: &Coordinate, dx: isize, dy: isize| {
+            let x = c.0 as isize + dx;
+            let y = c.1 as isize + dy;
+            Some(Coordinate(x as usize, y as usize))
+                .filter(|c| x >= 0 && y >= 0 && self.contains(c))
+        };
+
+        for dy in -1..=1isize {
+            for dx in -1..=1isize {
+                if (dx, dy) == (0, 0) {
+                    continue;
+                }
+
+                let first = step(coordinate, dx, dy);
+                let second = first.and_then(|c| step(&c, dx, dy));
+                let anchor = second.and_then(|c| step(&c, dx, dy));
+                if let (Some(first), Some(second), Some(anchor)) = (first, second, anchor) {
+                    let enemy = self.get_space(&first);
+                    if enemy.get_player().is_some_and(|p| p != *player)
+                        && self.get_space(&second) == enemy
+                        && self.get_space(&anchor) == own
+                    {
+                        captured.push(first);
+                        captured.push(second);
+                    }
+                }
+            }
+        }
+
+        captured
+    }
+
+    // Empties a space again, eg. when a piece moves off it.
+    pub fn clear_space(&mut self, coordinate: &Coordinate) {
+        let index = self.index(coordinate);
+        self.spaces[index] = Space::Empty;
+    }
+
+    // Takes a space out of play, for boards that aren't rectangular.
+    pub fn block(&mut self, coordinate: &Coordinate) {
+        let index = self.index(coordinate);
+        self.spaces[index] = Space::Blocked;
+    }
+
+    // The up to eight spaces surrounding a coordinate, whether occupied or not.
+    pub fn neighbours(&self, coordinate: &Coordinate) -> Vec<Coordinate> {
+        let mut neighbours = Vec::with_capacity(8);
+        for dy in -1..=1isize {
+            for dx in -1..=1isize {
+                let x = coordinate.0 as isize + dx;
+                let y = coordinate.1 as isize + dy;
+                if (dx, dy) != (0, 0) && x >= 0 && y >= 0 {
+                    let neighbour = Coordinate(x as usize, y as usize);
+                    if self.contains(&neighbour) {
+                        neighbours.push(neighbour);
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    pub fn count(&self, player: &Player) -> usize {
+        let space = Space::new(Some(player));
+        self.spaces.iter().filter(|s| **s == space).count()
+    }
+
+    pub fn lines(&self) -> LineIterator<'_> {
+        LineIterator::new(self)
     }
 
-    pub fn lines(&self) -> LineIterator {
-        LineIterator::new(self.spaces.clone())
+    pub fn lines_through(&self, coordinate: &Coordinate) -> impl Iterator<Item = Line> + '_ {
+        self.lines
+            .through(coordinate)
+            .map(move |profile| Line(profile.iter().map(|c| (*c, self.get_space(c))).collect()))
     }
 
     pub fn get_winner(&self) -> Option<Player> {
@@ -55,18 +256,38 @@ impl Grid {
                 return Some(winner);
             }
         }
-        None
+        Player::ALL
+            .iter()
+            .copied()
+            .find(|p| self.captured(p) >= Grid::CAPTURES_TO_WIN)
+    }
+
+    pub fn has_line(&self, player: &Player) -> bool {
+        self.lines().any(|line| line.get_winner() == Some(*player))
+    }
+
+    // A line, or enough captured pairs when the capture rule is on.
+    pub fn has_won(&self, player: &Player) -> bool {
+        self.has_line(player) || self.captured(player) >= Grid::CAPTURES_TO_WIN
+    }
+
+    // Would playing the given (empty) space complete a line for the player? Only the
+    // lines running through the space are checked, so this stays cheap on big boards.
+    pub fn wins_with(&self, coordinate: &Coordinate, player: &Player) -> bool {
+        let space = Space::new(Some(player));
+        self.lines.through(coordinate).any(|profile| {
+            profile
+                .iter()
+                .all(|c| c == coordinate || self.get_space(c) == space)
+        })
     }
 
     pub fn has_legal_moves(&self) -> bool {
-        for x in 0..=2 {
-            for y in 0..=2 {
-                if self.is_legal(&Coordinate::new(x, y)) {
-                    return true;
-                }
-            }
-        }
-        false
+        self.spaces.contains(&Space::Empty)
+    }
+
+    fn index(&self, coordinate: &Coordinate) -> usize {
+        coordinate.1 * self.width() + coordinate.0
     }
 
     pub fn is_in_progress(&self) -> bool {
@@ -74,6 +295,15 @@ impl Grid {
     }
 }
 
+// Grids are mostly compared against others with the same lines, so only the spaces (and
+// any capture counts) are worth hashing.
+impl Hash for Grid {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.spaces.hash(state);
+        self.captures.hash(state);
+    }
+}
+
 impl fmt::Display for Grid {
     //     A   B   C
     //   +---+---+---+
@@ -84,14 +314,21 @@ impl fmt::Display for Grid {
     // 3 |   |   | O |
     //   +---+---+---+
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "     A   B   C")?;
-        writeln!(f, "   +---+---+---+")?;
-
-        let mut row_num = 0;
-        for row in self.spaces.iter() {
-            row_num = row_num + 1;
-            writeln!(f, " {} | {} | {} | {} |", row_num, row[0], row[1], row[2])?;
-            writeln!(f, "   +---+---+---+")?;
+        let border = "+---".repeat(self.width());
+
+        let columns: Vec<String> = (0..self.width())
+            .map(|x| Coordinate::column_name(x).to_string())
+            .collect();
+        writeln!(f, "     {}", columns.join("   "))?;
+        writeln!(f, "   {}+", border)?;
+
+        for (y, row) in self.spaces.chunks(self.width()).enumerate() {
+            write!(f, "{:>2} |", y + 1)?;
+            for space in row {
+                write!(f, " {} |", space)?;
+            }
+            writeln!(f)?;
+            writeln!(f, "   {}+", border)?;
         }
 
         Ok(())
@@ -104,16 +341,24 @@ mod test_grid {
 
     #[test]
     fn empty() {
-        assert_eq!(
-            Grid {
-                spaces: [
-                    [Space::Empty, Space::Empty, Space::Empty],
-                    [Space::Empty, Space::Empty, Space::Empty],
-                    [Space::Empty, Space::Empty, Space::Empty],
-                ]
-            },
-            Grid::empty(),
-        );
+        assert_eq!(vec![Space::Empty; 9], Grid::empty().spaces,);
+    }
+
+    #[test]
+    fn with_size() {
+        let grid = Grid::with_size(5, 4, 4);
+        assert_eq!(5, grid.width());
+        assert_eq!(4, grid.height());
+        assert_eq!(20, grid.legal_moves().len());
+
+        // 4 rows * 2 positions + 5 columns + 2 diagonals * 2 directions
+        assert_eq!(17, grid.lines().len());
+    }
+
+    #[test]
+    #[should_panic(expected = "Lines must be between 1 and 5 spaces long on a 5x4 board")]
+    fn with_size_too_long() {
+        Grid::with_size(5, 4, 6);
     }
 
     #[test]
@@ -137,8 +382,28 @@ mod test_grid {
             [Space::Empty, Space::Empty, Space::Empty],
         ]);
 
-        assert_eq!(true, grid.is_legal(&Coordinate(0, 1)));
-        assert_eq!(false, grid.is_legal(&Coordinate(1, 0)));
+        assert!(grid.is_legal(&Coordinate(0, 1)));
+        assert!(!grid.is_legal(&Coordinate(1, 0)));
+        assert!(!grid.is_legal(&Coordinate(3, 0)));
+        assert!(!grid.is_legal(&Coordinate(0, 3)));
+        assert!(!grid.is_legal(&Coordinate(0, 11)));
+    }
+
+    #[test]
+    fn try_legal_off_board() {
+        let grid = Grid::empty();
+        assert_eq!(
+            Err(IllegalMove(Coordinate(3, 0))),
+            grid.try_legal(&"D1".parse().unwrap()),
+        );
+        assert_eq!(
+            Err(IllegalMove(Coordinate(0, 3))),
+            grid.try_legal(&"A4".parse().unwrap()),
+        );
+        assert_eq!(
+            Err(IllegalMove(Coordinate(0, 11))),
+            grid.try_legal(&"A12".parse().unwrap()),
+        );
     }
 
     #[test]
@@ -150,12 +415,16 @@ mod test_grid {
             grid.set_space(&Coordinate(0, 2), &Player::O),
         );
         assert_eq!(Ok(()), grid.set_space(&Coordinate(2, 0), &Player::O));
+        assert_eq!(Ok(()), grid.set_space(&Coordinate(1, 1), &Player::Delta));
+        assert_eq!(1, grid.count(&Player::X));
+        assert_eq!(1, grid.count(&Player::Delta));
+        assert_eq!(0, grid.count(&Player::Square));
     }
 
     #[test]
     fn lines() {
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 0), Space::O),
                 (Coordinate(1, 0), Space::X),
                 (Coordinate(2, 0), Space::O),
@@ -189,6 +458,171 @@ mod test_grid {
         assert!(!grid.is_in_progress());
     }
 
+    #[test]
+    fn has_line() {
+        let grid = Grid::new([
+            [Space::O, Space::X, Space::Delta],
+            [Space::Square, Space::O, Space::Delta],
+            [Space::Empty, Space::X, Space::Delta],
+        ]);
+        assert!(grid.has_line(&Player::Delta));
+        assert!(!grid.has_line(&Player::O));
+        assert!(!grid.has_line(&Player::X));
+    }
+
+    #[test]
+    fn wins_with() {
+        let mut grid = Grid::with_size(5, 5, 4);
+        grid.set_space(&Coordinate(0, 1), &Player::X).unwrap();
+        grid.set_space(&Coordinate(1, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate(3, 4), &Player::X).unwrap();
+
+        assert!(grid.wins_with(&Coordinate(2, 3), &Player::X));
+        assert!(!grid.wins_with(&Coordinate(2, 3), &Player::O));
+        assert!(!grid.wins_with(&Coordinate(4, 4), &Player::X));
+    }
+
+    #[test]
+    fn with_winning_sets() {
+        // An L shape and a pair that share a space
+        let mut grid = Grid::with_winning_sets(
+            3,
+            2,
+            vec![
+                vec![Coordinate(0, 0), Coordinate(0, 1), Coordinate(1, 1)],
+                vec![Coordinate(1, 1), Coordinate(2, 0)],
+            ],
+        );
+        assert_eq!(2, grid.lines().len());
+        assert_eq!(2, grid.lines_through(&Coordinate(1, 1)).count());
+        assert_eq!(0, grid.lines_through(&Coordinate(1, 0)).count());
+
+        grid.set_space(&Coordinate(2, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate(1, 0), &Player::X).unwrap();
+        assert_eq!(None, grid.get_winner());
+        assert!(grid.wins_with(&Coordinate(1, 1), &Player::X));
+
+        grid.set_space(&Coordinate(1, 1), &Player::X).unwrap();
+        assert_eq!(Some(Player::X), grid.get_winner());
+    }
+
+    #[test]
+    #[should_panic(expected = "Winning sets must fit on the board")]
+    fn with_winning_sets_off_board() {
+        Grid::with_winning_sets(3, 3, vec![vec![Coordinate(0, 0), Coordinate(3, 0)]]);
+    }
+
+    #[test]
+    fn captures() {
+        // X brackets the O pair on the row and the diagonal with a single stone.
+        let mut grid = Grid::with_size(6, 6, 5).with_captures();
+        for (x, y) in [(1, 0), (2, 0), (1, 1), (2, 2)].iter() {
+            grid.set_space(&Coordinate(*x, *y), &Player::O).unwrap();
+        }
+        grid.set_space(&Coordinate(3, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate(3, 3), &Player::X).unwrap();
+
+        assert_eq!(
+            vec![
+                Coordinate(1, 0),
+                Coordinate(2, 0),
+                Coordinate(1, 1),
+                Coordinate(2, 2),
+            ],
+            grid.captures_with(&Coordinate(0, 0), &Player::X),
+        );
+        grid.set_space(&Coordinate(0, 0), &Player::X).unwrap();
+        assert_eq!(2, grid.captured(&Player::X));
+        assert_eq!(0, grid.count(&Player::O));
+        assert!(grid.is_legal(&Coordinate(1, 1)));
+    }
+
+    #[test]
+    fn captures_only_pairs() {
+        let mut grid = Grid::with_size(6, 1, 6).with_captures();
+        grid.set_space(&Coordinate(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate(1, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate(2, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate(3, 0), &Player::O).unwrap();
+
+        // Three in a row can't be captured
+        assert!(grid.captures_with(&Coordinate(4, 0), &Player::X).is_empty());
+
+        // Without the rule, nothing is ever captured
+        let mut grid = Grid::with_size(4, 1, 4);
+        grid.set_space(&Coordinate(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate(1, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate(2, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate(3, 0), &Player::X).unwrap();
+        assert_eq!(2, grid.count(&Player::O));
+        assert!(!grid.has_captures());
+    }
+
+    #[test]
+    fn captures_win() {
+        // Five O pairs on every other row, each captured by X closing the bracket.
+        let mut grid = Grid::with_size(4, 9, 5).with_captures();
+        for y in (0..9).step_by(2) {
+            grid.set_space(&Coordinate(0, y), &Player::X).unwrap();
+            grid.set_space(&Coordinate(1, y), &Player::O).unwrap();
+            grid.set_space(&Coordinate(2, y), &Player::O).unwrap();
+        }
+        for y in (0..8).step_by(2) {
+            grid.set_space(&Coordinate(3, y), &Player::X).unwrap();
+        }
+        assert_eq!(4, grid.captured(&Player::X));
+        assert_eq!(None, grid.get_winner());
+        assert!(!grid.has_won(&Player::X));
+
+        grid.set_space(&Coordinate(3, 8), &Player::X).unwrap();
+        assert_eq!(Some(Player::X), grid.get_winner());
+        assert!(grid.has_won(&Player::X));
+        assert!(!grid.has_line(&Player::X));
+        assert!(!grid.is_in_progress());
+    }
+
+    #[test]
+    fn block() {
+        let mut grid = Grid::empty();
+        grid.block(&Coordinate(1, 1));
+        assert!(!grid.is_legal(&Coordinate(1, 1)));
+        assert_eq!(8, grid.legal_moves().len());
+        assert_eq!(
+            Err(IllegalMove(Coordinate(1, 1))),
+            grid.set_space(&Coordinate(1, 1), &Player::X),
+        );
+    }
+
+    #[test]
+    fn clear_space() {
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate(1, 1), &Player::X).unwrap();
+        grid.clear_space(&Coordinate(1, 1));
+        assert_eq!(Grid::empty(), grid);
+    }
+
+    #[test]
+    fn neighbours() {
+        let grid = Grid::empty();
+        assert_eq!(8, grid.neighbours(&Coordinate(1, 1)).len());
+        assert_eq!(
+            vec![Coordinate(1, 0), Coordinate(0, 1), Coordinate(1, 1)],
+            grid.neighbours(&Coordinate(0, 0)),
+        );
+        assert_eq!(5, grid.neighbours(&Coordinate(2, 1)).len());
+    }
+
+    #[test]
+    fn lines_through() {
+        let grid = Grid::empty();
+        assert_eq!(4, grid.lines_through(&Coordinate(1, 1)).count());
+        assert_eq!(3, grid.lines_through(&Coordinate(2, 0)).count());
+        assert_eq!(2, grid.lines_through(&Coordinate(0, 1)).count());
+        assert!(grid
+            .lines_through(&Coordinate(0, 1))
+            .all(|line| line.get_coordinates().contains(&Coordinate(0, 1))));
+    }
+
     #[test]
     fn has_legal_moves() {
         let grid = Grid::empty();
@@ -233,23 +667,40 @@ mod test_grid {
             format!("{}", grid),
         );
     }
+
+    #[test]
+    fn display_large() {
+        let mut grid = Grid::with_size(4, 10, 4);
+        grid.set_space(&Coordinate(3, 9), &Player::Square).unwrap();
+
+        let output = format!("{}", grid);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!("     A   B   C   D", lines[0]);
+        assert_eq!("   +---+---+---+---+", lines[1]);
+        assert_eq!(" 1 |   |   |   |   |", lines[2]);
+        assert_eq!("10 |   |   |   | □ |", lines[20]);
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct Line([(Coordinate, Space); 3]);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line(Vec<(Coordinate, Space)>);
 
 impl Line {
     pub fn get_winner(&self) -> Option<Player> {
-        let spaces = &self.get_spaces();
-        if spaces[0] == spaces[1] && spaces[0] == spaces[2] {
+        let spaces = self.get_spaces();
+        if spaces.iter().all(|space| *space == spaces[0]) {
             spaces[0].get_player()
         } else {
             None
         }
     }
 
-    fn get_spaces(&self) -> [Space; 3] {
-        [self.0[0].1, self.0[1].1, self.0[2].1]
+    pub fn get_coordinates(&self) -> Vec<Coordinate> {
+        self.0.iter().map(|(coordinate, _)| *coordinate).collect()
+    }
+
+    pub fn get_spaces(&self) -> Vec<Space> {
+        self.0.iter().map(|(_, space)| *space).collect()
     }
 }
 
@@ -258,7 +709,7 @@ mod test_line {
     use super::{Coordinate, Line, Player, Space};
 
     fn get_line(spaces: [Space; 3]) -> Line {
-        Line([
+        Line(vec![
             (Coordinate(0, 0), spaces[0]),
             (Coordinate(1, 0), spaces[1]),
             (Coordinate(2, 0), spaces[2]),
@@ -285,54 +736,196 @@ mod test_line {
     }
 }
 
-pub struct LineIterator {
-    spaces: [[Space; 3]; 3],
+pub struct LineIterator<'a> {
+    grid: &'a Grid,
     counter: usize,
 }
 
-impl LineIterator {
-    const LINE_PROFILES: [[[usize; 2]; 3]; 8] = [
-        [[0, 0], [1, 0], [2, 0]], // row 1
-        [[0, 1], [1, 1], [2, 1]], // row 2
-        [[0, 2], [1, 2], [2, 2]], // row 3
-        [[0, 0], [0, 1], [0, 2]], // column A
-        [[1, 0], [1, 1], [1, 2]], // column B
-        [[2, 0], [2, 1], [2, 2]], // column C
-        [[0, 0], [1, 1], [2, 2]], // diagonal \
-        [[0, 2], [1, 1], [2, 0]], // diagonal /
-    ];
-
-    pub fn new(spaces: [[Space; 3]; 3]) -> LineIterator {
-        LineIterator { spaces, counter: 0 }
+impl<'a> LineIterator<'a> {
+    pub fn new(grid: &'a Grid) -> LineIterator<'a> {
+        LineIterator { grid, counter: 0 }
     }
 }
 
-impl iter::Iterator for LineIterator {
+impl<'a> iter::Iterator for LineIterator<'a> {
     type Item = Line;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.counter < Self::LINE_PROFILES.len() {
-            let profile = Self::LINE_PROFILES[self.counter];
-            let x = [profile[0][0], profile[1][0], profile[2][0]];
-            let y = [profile[0][1], profile[1][1], profile[2][1]];
-            self.counter += 1;
-
-            Some(Line([
-                (Coordinate(x[0], y[0]), self.spaces[y[0]][x[0]]),
-                (Coordinate(x[1], y[1]), self.spaces[y[1]][x[1]]),
-                (Coordinate(x[2], y[2]), self.spaces[y[2]][x[2]]),
-            ]))
-        } else {
-            None
-        }
+        let profile = self.grid.lines.profiles.get(self.counter)?;
+        self.counter += 1;
+
+        Some(Line(
+            profile
+                .iter()
+                .map(|coordinate| (*coordinate, self.grid.get_space(coordinate)))
+                .collect(),
+        ))
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
-        (8, Some(8))
+        let remaining = self.grid.lines.profiles.len() - self.counter;
+        (remaining, Some(remaining))
     }
 }
 
-impl iter::ExactSizeIterator for LineIterator {}
+impl<'a> iter::ExactSizeIterator for LineIterator<'a> {}
+
+// The winning sets on the board, plus an index of which sets include each space. Unless
+// the board was given its own sets, these are every straight run of `line_length`
+// spaces. Shared between clones of a grid, since they never change.
+#[derive(Debug, PartialEq, Eq)]
+struct Lines {
+    width: usize,
+    profiles: Vec<Vec<Coordinate>>,
+    by_space: Vec<Vec<usize>>,
+}
+
+impl Lines {
+    fn new(width: usize, height: usize, line_length: usize) -> Lines {
+        Lines::from_shapes(width, height, &Shape::lines(line_length))
+    }
+
+    fn from_shapes(width: usize, height: usize, shapes: &[Shape]) -> Lines {
+        let profiles = shapes
+            .iter()
+            .flat_map(|shape| shape.placements(width, height))
+            .collect();
+        Lines::from_profiles(width, height, profiles)
+    }
+
+    fn from_profiles(width: usize, height: usize, profiles: Vec<Vec<Coordinate>>) -> Lines {
+        let mut by_space = vec![Vec::new(); width * height];
+        for (i, profile) in profiles.iter().enumerate() {
+            for coordinate in profile {
+                by_space[coordinate.1 * width + coordinate.0].push(i);
+            }
+        }
+
+        Lines {
+            width,
+            profiles,
+            by_space,
+        }
+    }
+
+    fn through<'a>(&'a self, coordinate: &Coordinate) -> impl Iterator<Item = &'a Vec<Coordinate>> {
+        self.by_space[coordinate.1 * self.width + coordinate.0]
+            .iter()
+            .map(move |i| &self.profiles[*i])
+    }
+}
+
+// A winning pattern, given as offsets from an anchor space. It counts anywhere on the
+// board it can be translated to without running off the edge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shape(Vec<(isize, isize)>);
+
+impl Shape {
+    pub fn new(offsets: Vec<(isize, isize)>) -> Shape {
+        assert!(!offsets.is_empty(), "Shapes can't be empty");
+        Shape(offsets)
+    }
+
+    pub fn line(dx: isize, dy: isize, length: usize) -> Shape {
+        Shape::new((0..length as isize).map(|i| (dx * i, dy * i)).collect())
+    }
+
+    // Straight lines along rows, columns and both diagonals.
+    pub fn lines(length: usize) -> Vec<Shape> {
+        vec![
+            Shape::line(1, 0, length),  // rows
+            Shape::line(0, 1, length),  // columns
+            Shape::line(1, 1, length),  // diagonal \
+            Shape::line(1, -1, length), // diagonal /
+        ]
+    }
+
+    pub fn square(size: usize) -> Shape {
+        let size = size as isize;
+        Shape::new(
+            (0..size)
+                .flat_map(|dy| (0..size).map(move |dx| (dx, dy)))
+                .collect(),
+        )
+    }
+
+    pub fn get_offsets(&self) -> &[(isize, isize)] {
+        &self.0
+    }
+
+    // Every way the shape fits on a board, anchored row by row. Shapes that reach upwards
+    // start from the bottom of the board.
+    pub fn placements(&self, width: usize, height: usize) -> Vec<Vec<Coordinate>> {
+        let rows: Vec<usize> = if self.0.iter().any(|(_, dy)| *dy < 0) {
+            (0..height).rev().collect()
+        } else {
+            (0..height).collect()
+        };
+
+        let mut placements = Vec::new();
+        for y in rows {
+            for x in 0..width {
+                let placement: Option<Vec<Coordinate>> = self
+                    .0
+                    .iter()
+                    .map(|(dx, dy)| {
+                        let x = x as isize + dx;
+                        let y = y as isize + dy;
+                        if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                            Some(Coordinate(x as usize, y as usize))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+
+                if let Some(placement) = placement {
+                    placements.push(placement);
+                }
+            }
+        }
+        placements
+    }
+}
+
+#[cfg(test)]
+mod test_shape {
+    use super::{Coordinate, Grid, Player, Shape};
+
+    #[test]
+    fn placements() {
+        assert_eq!(
+            vec![
+                vec![Coordinate(0, 0), Coordinate(1, 0)],
+                vec![Coordinate(0, 1), Coordinate(1, 1)],
+            ],
+            Shape::line(1, 0, 2).placements(2, 2),
+        );
+        assert_eq!(4, Shape::square(2).placements(3, 3).len());
+        assert!(Shape::square(4).placements(3, 3).is_empty());
+    }
+
+    #[test]
+    fn custom_shape() {
+        // An L tromino, tried everywhere on a 3x3 board
+        let shape = Shape::new(vec![(0, 0), (0, 1), (1, 1)]);
+        let mut grid = Grid::with_shapes(3, 3, &[shape]);
+        assert_eq!(4, grid.lines().len());
+
+        grid.set_space(&Coordinate(1, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate(1, 2), &Player::O).unwrap();
+        assert!(grid.wins_with(&Coordinate(2, 2), &Player::O));
+        assert!(!grid.wins_with(&Coordinate(0, 2), &Player::O));
+    }
+
+    #[test]
+    fn lines_match_with_size() {
+        assert_eq!(
+            Grid::with_size(5, 4, 4),
+            Grid::with_shapes(5, 4, &Shape::lines(4)),
+        );
+    }
+}
 
 #[cfg(test)]
 mod test_line_iterator {
@@ -341,14 +934,15 @@ mod test_line_iterator {
     #[test]
     fn test_iterator() {
         // This grid should be identifiably different for each line
-        let mut iterator = LineIterator::new([
+        let grid = Grid::new([
             [Space::O, Space::X, Space::X],
             [Space::Empty, Space::X, Space::Empty],
             [Space::Empty, Space::Empty, Space::Empty],
         ]);
+        let mut iterator = LineIterator::new(&grid);
 
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 0), Space::O),
                 (Coordinate(1, 0), Space::X),
                 (Coordinate(2, 0), Space::X),
@@ -356,7 +950,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 1), Space::Empty),
                 (Coordinate(1, 1), Space::X),
                 (Coordinate(2, 1), Space::Empty),
@@ -364,7 +958,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 2), Space::Empty),
                 (Coordinate(1, 2), Space::Empty),
                 (Coordinate(2, 2), Space::Empty),
@@ -372,7 +966,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 0), Space::O),
                 (Coordinate(0, 1), Space::Empty),
                 (Coordinate(0, 2), Space::Empty),
@@ -380,7 +974,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(1, 0), Space::X),
                 (Coordinate(1, 1), Space::X),
                 (Coordinate(1, 2), Space::Empty),
@@ -388,7 +982,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(2, 0), Space::X),
                 (Coordinate(2, 1), Space::Empty),
                 (Coordinate(2, 2), Space::Empty),
@@ -396,7 +990,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 0), Space::O),
                 (Coordinate(1, 1), Space::X),
                 (Coordinate(2, 2), Space::Empty),
@@ -404,7 +998,7 @@ mod test_line_iterator {
             iterator.next(),
         );
         assert_eq!(
-            Some(Line([
+            Some(Line(vec![
                 (Coordinate(0, 2), Space::Empty),
                 (Coordinate(1, 1), Space::X),
                 (Coordinate(2, 0), Space::X),
@@ -428,29 +1022,39 @@ impl Coordinate {
     pub fn new(x: usize, y: usize) -> Coordinate {
         Coordinate(x, y)
     }
+
+    pub fn x(&self) -> usize {
+        self.0
+    }
+
+    pub fn y(&self) -> usize {
+        self.1
+    }
+
+    pub fn column_name(x: usize) -> char {
+        (b'A' + x as u8) as char
+    }
 }
 
 impl FromStr for Coordinate {
     type Err = ParseCoordinateError;
 
     fn from_str(raw: &str) -> Result<Self, Self::Err> {
-        if raw.len() != 2 {
-            Err(ParseCoordinateError(raw.to_string()))
-        } else {
-            Ok(Coordinate(
-                match raw.chars().nth(0) {
-                    Some('A') | Some('a') => 0,
-                    Some('B') | Some('b') => 1,
-                    Some('C') | Some('c') => 2,
-                    _ => return Err(ParseCoordinateError(raw.to_string())),
-                },
-                match raw.chars().nth(1) {
-                    Some('1') => 0,
-                    Some('2') => 1,
-                    Some('3') => 2,
-                    _ => return Err(ParseCoordinateError(raw.to_string())),
-                },
-            ))
+        let error = || ParseCoordinateError(raw.to_string());
+
+        let mut chars = raw.chars();
+        let x = match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase() as usize - 'A' as usize,
+            _ => return Err(error()),
+        };
+
+        let row = chars.as_str();
+        if row.is_empty() || !row.chars().all(|c| c.is_ascii_digit()) {
+            return Err(error());
+        }
+        match row.parse::<usize>() {
+            Ok(y) if y > 0 => Ok(Coordinate(x, y - 1)),
+            _ => Err(error()),
         }
     }
 }
@@ -463,19 +1067,7 @@ impl From<Coordinate> for String {
 
 impl fmt::Display for Coordinate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.0 {
-            0 => write!(f, "A")?,
-            1 => write!(f, "B")?,
-            2 => write!(f, "C")?,
-            _ => unreachable!(),
-        };
-        match self.1 {
-            0 => write!(f, "1")?,
-            1 => write!(f, "2")?,
-            2 => write!(f, "3")?,
-            _ => unreachable!(),
-        };
-        Ok(())
+        write!(f, "{}{}", Coordinate::column_name(self.0), self.1 + 1)
     }
 }
 
@@ -521,6 +1113,18 @@ mod test_coordinate {
             "{:?}",
             "c1",
         );
+        assert_eq!(
+            Ok(Coordinate(3, 0)),
+            "D1".parse::<Coordinate>(),
+            "{:?}",
+            "D1",
+        );
+        assert_eq!(
+            Ok(Coordinate(18, 18)),
+            "s19".parse::<Coordinate>(),
+            "{:?}",
+            "s19",
+        );
     }
 
     #[test]
@@ -532,22 +1136,34 @@ mod test_coordinate {
             "",
         );
         assert_eq!(
-            Err(ParseCoordinateError("D1".to_string())),
-            "D1".parse::<Coordinate>(),
+            Err(ParseCoordinateError("A".to_string())),
+            "A".parse::<Coordinate>(),
             "{:?}",
-            "D1",
+            "A",
         );
         assert_eq!(
-            Err(ParseCoordinateError("A4".to_string())),
-            "A4".parse::<Coordinate>(),
+            Err(ParseCoordinateError("A0".to_string())),
+            "A0".parse::<Coordinate>(),
             "{:?}",
-            "A4",
+            "A0",
         );
         assert_eq!(
-            Err(ParseCoordinateError("A12".to_string())),
-            "A12".parse::<Coordinate>(),
+            Err(ParseCoordinateError("1A".to_string())),
+            "1A".parse::<Coordinate>(),
             "{:?}",
-            "A12",
+            "1A",
+        );
+        assert_eq!(
+            Err(ParseCoordinateError("A1B".to_string())),
+            "A1B".parse::<Coordinate>(),
+            "{:?}",
+            "A1B",
+        );
+        assert_eq!(
+            Err(ParseCoordinateError("Ä1".to_string())),
+            "Ä1".parse::<Coordinate>(),
+            "{:?}",
+            "Ä1",
         );
     }
 
@@ -563,6 +1179,7 @@ mod test_coordinate {
         assert_eq!("A3", format!("{}", Coordinate(0, 2)));
         assert_eq!("B1", format!("{}", Coordinate(1, 0)));
         assert_eq!("C2", format!("{}", Coordinate(2, 1)));
+        assert_eq!("K10", format!("{}", Coordinate(10, 9)));
     }
 }
 
@@ -614,13 +1231,36 @@ mod test_illegal_move {
 pub enum Player {
     X,
     O,
+    Delta,
+    Square,
 }
 
 impl Player {
+    pub const ALL: [Player; 4] = [Player::X, Player::O, Player::Delta, Player::Square];
+
+    // The next player in a two-player game, X and O. Games with more players pass their
+    // own seat order around instead, so what this returns for Delta and Square (Square,
+    // then back to X) isn't a turn order anyone should rely on.
     pub fn turn(&self) -> Self {
         match self {
             Self::X => Self::O,
             Self::O => Self::X,
+            Self::Delta => Self::Square,
+            Self::Square => Self::X,
+        }
+    }
+
+    pub fn opponents(&self) -> impl Iterator<Item = Player> {
+        let player = *self;
+        Player::ALL.iter().copied().filter(move |p| *p != player)
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            Self::X => 0,
+            Self::O => 1,
+            Self::Delta => 2,
+            Self::Square => 3,
         }
     }
 }
@@ -630,15 +1270,47 @@ impl fmt::Display for Player {
         match self {
             Self::X => write!(f, "X"),
             Self::O => write!(f, "O"),
+            Self::Delta => write!(f, "Δ"),
+            Self::Square => write!(f, "□"),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg(test)]
+mod test_player {
+    use super::Player;
+
+    #[test]
+    fn turn() {
+        assert_eq!(Player::O, Player::X.turn());
+        assert_eq!(Player::X, Player::O.turn());
+    }
+
+    #[test]
+    fn opponents() {
+        assert_eq!(
+            vec![Player::X, Player::Delta, Player::Square],
+            Player::O.opponents().collect::<Vec<Player>>(),
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("X", &format!("{}", Player::X));
+        assert_eq!("O", &format!("{}", Player::O));
+        assert_eq!("Δ", &format!("{}", Player::Delta));
+        assert_eq!("□", &format!("{}", Player::Square));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Space {
     X,
     O,
+    Delta,
+    Square,
     Empty,
+    Blocked,
 }
 
 impl Space {
@@ -646,6 +1318,8 @@ impl Space {
         match player {
             Some(Player::X) => Space::X,
             Some(Player::O) => Space::O,
+            Some(Player::Delta) => Space::Delta,
+            Some(Player::Square) => Space::Square,
             None => Space::Empty,
         }
     }
@@ -654,17 +1328,19 @@ impl Space {
         match self {
             Space::X => Some(Player::X),
             Space::O => Some(Player::O),
-            Space::Empty => None,
+            Space::Delta => Some(Player::Delta),
+            Space::Square => Some(Player::Square),
+            Space::Empty | Space::Blocked => None,
         }
     }
 }
 
 impl fmt::Display for Space {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self {
-            Space::X => write!(f, "X"),
-            Space::O => write!(f, "O"),
-            Space::Empty => write!(f, " "),
+        match self.get_player() {
+            Some(player) => write!(f, "{}", player),
+            None if *self == Space::Blocked => write!(f, "#"),
+            None => write!(f, " "),
         }
     }
 }
@@ -677,6 +1353,8 @@ mod test_space {
     fn new() {
         assert_eq!(Space::X, Space::new(Some(&Player::X)));
         assert_eq!(Space::O, Space::new(Some(&Player::O)));
+        assert_eq!(Space::Delta, Space::new(Some(&Player::Delta)));
+        assert_eq!(Space::Square, Space::new(Some(&Player::Square)));
         assert_eq!(Space::Empty, Space::new(None));
     }
 
@@ -684,13 +1362,19 @@ mod test_space {
     fn get_player() {
         assert_eq!(Some(Player::X), Space::X.get_player());
         assert_eq!(Some(Player::O), Space::O.get_player());
+        assert_eq!(Some(Player::Delta), Space::Delta.get_player());
+        assert_eq!(Some(Player::Square), Space::Square.get_player());
         assert_eq!(None, Space::Empty.get_player());
+        assert_eq!(None, Space::Blocked.get_player());
     }
 
     #[test]
     fn display() {
         assert_eq!("X", &format!("{}", Space::X));
         assert_eq!("O", &format!("{}", Space::O));
+        assert_eq!("Δ", &format!("{}", Space::Delta));
+        assert_eq!("□", &format!("{}", Space::Square));
         assert_eq!(" ", &format!("{}", Space::Empty));
+        assert_eq!("#", &format!("{}", Space::Blocked));
     }
 }
@@ -0,0 +1,480 @@
+use super::computer::{ComputerPlayer, Difficulty};
+use super::rando::RandoPlayer;
+use super::strategy::{self, Context, Strategy};
+use super::{play, Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::fmt;
+use std::rc::Rc;
+
+// Evolves players built from the same strategies as `ComputerPlayer`'s chain. A genome
+// gives every strategy a weight and a place in an order. Each legal move scores the total
+// weight of the strategies that accept it, and ties go to the move accepted by the
+// strategy that comes first. Fitness is the share of points won in a tournament against reference
+// players, and each generation keeps the best few and breeds the rest from tournament
+// selection, crossover and mutation.
+pub struct Evolution {
+    population: Vec<Genome>,
+    references: Vec<Reference>,
+    games: usize,
+    mutation_rate: f64,
+    rng: StdRng,
+}
+
+#[derive(Clone, Default)]
+pub struct Genome {
+    // Strategies in order of preference, with their weights
+    genes: Vec<(Rc<dyn Strategy>, f64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reference {
+    // Uniformly random moves, from a generator seeded by the evolution
+    Random,
+    Computer(Difficulty),
+}
+
+// The best genome in a generation, with its fitness and the population's average.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generation {
+    best: Genome,
+    best_fitness: f64,
+    mean_fitness: f64,
+}
+
+// Plays a genome.
+pub struct EvolvedPlayer {
+    genome: Genome,
+    player: Player,
+    rng: StdRng,
+}
+
+impl Genome {
+    // The strategies random genomes are made of
+    pub const STRATEGIES: [&'static str; 7] = [
+        "win",
+        "block",
+        "fork",
+        "block-fork",
+        "center",
+        "corner",
+        "edge",
+    ];
+
+    // Genomes are put together like pipelines:
+    //
+    //     Genome::new().with(Win, 1.0).with(Block, 0.5)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every strategy in a random order, with random weights between 0 and 1.
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        let mut names = Self::STRATEGIES.to_vec();
+        names.shuffle(rng);
+        names.into_iter().fold(Self::new(), |genome, name| {
+            genome.with_boxed(strategy::by_name(name).unwrap(), rng.gen())
+        })
+    }
+
+    pub fn with<S: Strategy + 'static>(mut self, strategy: S, weight: f64) -> Self {
+        self.genes.push((Rc::new(strategy), weight));
+        self
+    }
+
+    pub fn with_boxed(mut self, strategy: Box<dyn Strategy>, weight: f64) -> Self {
+        self.genes.push((Rc::from(strategy), weight));
+        self
+    }
+
+    pub fn get_genes(&self) -> &[(Rc<dyn Strategy>, f64)] {
+        &self.genes
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.genes.iter().map(|(s, _)| s.name()).collect()
+    }
+
+    pub fn player(&self, player: Player, seed: u64) -> EvolvedPlayer {
+        EvolvedPlayer {
+            genome: self.clone(),
+            player,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // The moves this genome likes best, in board order.
+    pub fn best_moves(&self, grid: &Grid, player: &Player) -> Vec<Coordinate> {
+        let moves = grid.legal_moves();
+        let context = Context::new(grid, *player, &moves);
+        let scored: Vec<(Coordinate, f64, Vec<bool>)> = moves
+            .iter()
+            .map(|c| {
+                let matches: Vec<bool> = self
+                    .genes
+                    .iter()
+                    .map(|(strategy, _)| strategy.accepts(&context, c))
+                    .collect();
+                let score = self
+                    .genes
+                    .iter()
+                    .zip(matches.iter())
+                    .filter(|(_, matched)| **matched)
+                    .map(|((_, weight), _)| weight)
+                    .sum();
+                (*c, score, matches)
+            })
+            .collect();
+
+        let best = scored
+            .iter()
+            .map(|(_, score, matches)| (*score, matches))
+            .max_by(|a, b| a.partial_cmp(b).unwrap());
+        match best {
+            Some((score, matches)) => scored
+                .iter()
+                .filter(|(_, s, m)| *s == score && m == matches)
+                .map(|(c, _, _)| *c)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Order crossover: a run of the first parent's order is kept in place and the other
+    // strategies fill in around it in the second parent's order. Each weight comes from
+    // one parent or the other.
+    fn crossover<R: Rng>(&self, other: &Genome, rng: &mut R) -> Genome {
+        let len = self.genes.len();
+        let start = rng.gen_range(0, len);
+        let end = rng.gen_range(start, len) + 1;
+        let kept: Vec<&Rc<dyn Strategy>> = self.genes[start..end].iter().map(|(s, _)| s).collect();
+        let mut rest = other
+            .genes
+            .iter()
+            .map(|(s, _)| s)
+            .filter(|s| kept.iter().all(|k| k.name() != s.name()));
+
+        let order: Vec<&Rc<dyn Strategy>> = (0..len)
+            .map(|i| {
+                if i >= start && i < end {
+                    kept[i - start]
+                } else {
+                    rest.next().unwrap()
+                }
+            })
+            .collect();
+
+        let weight = |genome: &Genome, name: &str| {
+            genome
+                .genes
+                .iter()
+                .find(|(s, _)| s.name() == name)
+                .unwrap()
+                .1
+        };
+        Genome {
+            genes: order
+                .into_iter()
+                .map(|strategy| {
+                    let parent = if rng.gen() { self } else { other };
+                    (strategy.clone(), weight(parent, strategy.name()))
+                })
+                .collect(),
+        }
+    }
+
+    // Each weight may be nudged, and two strategies may swap places.
+    fn mutate<R: Rng>(&mut self, rate: f64, rng: &mut R) {
+        for (_, weight) in self.genes.iter_mut() {
+            if rng.gen_bool(rate) {
+                *weight = (*weight + rng.gen_range(-0.25, 0.25)).max(0.0);
+            }
+        }
+        if rng.gen_bool(rate) {
+            let len = self.genes.len();
+            self.genes
+                .swap(rng.gen_range(0, len), rng.gen_range(0, len));
+        }
+    }
+}
+
+// Eg. `win 0.91 > block 0.87 > center 0.40 > ...`
+impl fmt::Display for Genome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let genes: Vec<String> = self
+            .genes
+            .iter()
+            .map(|(strategy, weight)| format!("{} {:.2}", strategy.name(), weight))
+            .collect();
+        write!(f, "{}", genes.join(" > "))
+    }
+}
+
+impl fmt::Debug for Genome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.genes.iter().map(|(s, weight)| (s.name(), weight)))
+            .finish()
+    }
+}
+
+// Genomes are equal when they have the same strategies, in the same order and with the
+// same weights.
+impl PartialEq for Genome {
+    fn eq(&self, other: &Self) -> bool {
+        self.names() == other.names()
+            && self
+                .genes
+                .iter()
+                .zip(other.genes.iter())
+                .all(|((_, a), (_, b))| a == b)
+    }
+}
+
+impl Evolution {
+    pub fn new(population: usize, seed: u64) -> Self {
+        assert!(population >= 2, "A population needs at least two genomes");
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self {
+            population: (0..population).map(|_| Genome::random(&mut rng)).collect(),
+            references: vec![Reference::Random, Reference::Computer(Difficulty::Hard)],
+            games: 10,
+            mutation_rate: 0.2,
+            rng,
+        }
+    }
+
+    pub fn with_references(mut self, references: Vec<Reference>) -> Self {
+        assert!(!references.is_empty(), "Fitness needs a reference player");
+        self.references = references;
+        self
+    }
+
+    // Games against each reference player from each side, per fitness evaluation
+    pub fn with_games(mut self, games: usize) -> Self {
+        self.games = games;
+        self
+    }
+
+    pub fn with_mutation_rate(mut self, mutation_rate: f64) -> Self {
+        self.mutation_rate = mutation_rate;
+        self
+    }
+
+    pub fn get_population(&self) -> &[Genome] {
+        &self.population
+    }
+
+    // Points won against the reference players, from 0 (lost every game) to 1 (won every
+    // game), with draws worth half.
+    pub fn fitness(&mut self, genome: &Genome) -> f64 {
+        let mut points = 0.0;
+        let mut games = 0;
+
+        for reference in self.references.clone().iter() {
+            for player in [Player::X, Player::O].iter() {
+                for _ in 0..self.games {
+                    let evolved: Box<dyn Playable> =
+                        Box::new(genome.player(*player, self.rng.gen()));
+                    let opponent: Box<dyn Playable> = match reference {
+                        Reference::Random => Box::new(RandoPlayer::seeded(self.rng.gen())),
+                        Reference::Computer(difficulty) => Box::new(
+                            ComputerPlayer::new_silent(player.turn())
+                                .with_difficulty(*difficulty)
+                                .with_seed(self.rng.gen()),
+                        ),
+                    };
+                    let grid = match player {
+                        Player::X => play(evolved, opponent),
+                        _ => play(opponent, evolved),
+                    };
+
+                    points += match grid.get_winner() {
+                        Some(winner) if winner == *player => 1.0,
+                        Some(_) => 0.0,
+                        None => 0.5,
+                    };
+                    games += 1;
+                }
+            }
+        }
+
+        points / games.max(1) as f64
+    }
+
+    // Scores the population, then replaces it with the next generation.
+    pub fn step(&mut self) -> Generation {
+        let population = self.population.clone();
+        let mut scored: Vec<(Genome, f64)> = population
+            .into_iter()
+            .map(|genome| {
+                let fitness = self.fitness(&genome);
+                (genome, fitness)
+            })
+            .collect();
+        scored.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
+
+        let generation = Generation {
+            best: scored[0].0.clone(),
+            best_fitness: scored[0].1,
+            mean_fitness: scored.iter().map(|(_, f)| f).sum::<f64>() / scored.len() as f64,
+        };
+
+        // The best two carry over unchanged.
+        let elite = 2.min(scored.len());
+        let mut next: Vec<Genome> = scored[..elite].iter().map(|(g, _)| g.clone()).collect();
+        while next.len() < scored.len() {
+            let first = self.select(&scored);
+            let second = self.select(&scored);
+            let mut child = first.crossover(second, &mut self.rng);
+            child.mutate(self.mutation_rate, &mut self.rng);
+            next.push(child);
+        }
+        self.population = next;
+
+        generation
+    }
+
+    pub fn run(&mut self, generations: usize) -> Vec<Generation> {
+        (0..generations).map(|_| self.step()).collect()
+    }
+
+    // The fittest of three genomes picked at random.
+    fn select<'a>(&mut self, scored: &'a [(Genome, f64)]) -> &'a Genome {
+        let mut best: Option<&(Genome, f64)> = None;
+        for _ in 0..3 {
+            let candidate = scored.choose(&mut self.rng).unwrap();
+            if best.is_none_or(|(_, fitness)| candidate.1 > *fitness) {
+                best = Some(candidate);
+            }
+        }
+        &best.unwrap().0
+    }
+}
+
+impl Generation {
+    pub fn get_best(&self) -> &Genome {
+        &self.best
+    }
+
+    pub fn get_best_fitness(&self) -> f64 {
+        self.best_fitness
+    }
+
+    pub fn get_mean_fitness(&self) -> f64 {
+        self.mean_fitness
+    }
+}
+
+impl Playable for EvolvedPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        *self
+            .genome
+            .best_moves(grid, &self.player)
+            .choose(&mut self.rng)
+            .expect("No legal moves!")
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+#[cfg(test)]
+mod test_genetic {
+    use super::{Evolution, Genome, Reference};
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::strategy::{Block, BlockFork, Center, Corner, Edge, Fork, Win};
+    use crate::Playable;
+
+    // Each strategy weighs more than all the ones after it put together.
+    fn classic() -> Genome {
+        Genome::new()
+            .with(Win, 1.0)
+            .with(Block, 0.5)
+            .with(Fork, 0.25)
+            .with(BlockFork, 0.125)
+            .with(Center, 0.0625)
+            .with(Corner, 0.03125)
+            .with(Edge, 0.015625)
+    }
+
+    #[test]
+    fn strategies() {
+        // XX.
+        // OO.
+        // ...
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+
+        let c1 = Coordinate::new(2, 0);
+        let c2 = Coordinate::new(2, 1);
+
+        // The classic ordering takes the win; one that only cares about blocking doesn't.
+        assert_eq!(vec![c1], classic().best_moves(&grid, &Player::X));
+        let blocker = Genome::new().with(Block, 1.0).with(Win, 0.5);
+        assert_eq!(vec![c2], blocker.best_moves(&grid, &Player::X));
+        assert_eq!(c2, blocker.player(Player::X, 0).play(&grid));
+
+        let edges = Genome::new().with(Edge, 1.0);
+        assert_eq!(
+            vec![Coordinate::new(1, 2), c2],
+            edges.best_moves(&grid, &Player::X),
+        );
+        assert_eq!(
+            "win 1.00 > block 0.50 > fork 0.25 > block-fork 0.12 > center 0.06 > corner 0.03 > \
+             edge 0.02",
+            format!("{}", classic()),
+        );
+    }
+
+    #[test]
+    fn ties_follow_the_order() {
+        // Both weigh the same, so the first strategy in the order wins the tie.
+        let corners = Genome::new().with(Corner, 1.0).with(Center, 1.0);
+        assert_eq!(4, corners.best_moves(&Grid::empty(), &Player::X).len());
+        let centre = Genome::new().with(Center, 1.0).with(Corner, 1.0);
+        assert_eq!(
+            vec![Coordinate::new(1, 1)],
+            centre.best_moves(&Grid::empty(), &Player::X),
+        );
+    }
+
+    #[test]
+    fn fitness() {
+        let mut evolution = Evolution::new(2, 1).with_games(10);
+        let good = evolution.fitness(&classic());
+        let bad = evolution.fitness(&Genome::new().with(Edge, 1.0));
+        assert!(good > bad);
+        assert!(good > 0.5);
+    }
+
+    #[test]
+    fn seeded_evolution() {
+        let run = || {
+            let mut evolution = Evolution::new(6, 2)
+                .with_references(vec![Reference::Random])
+                .with_games(3);
+            let generations = evolution.run(3);
+            (generations, evolution.get_population().to_vec())
+        };
+
+        let (generations, population) = run();
+        assert_eq!(3, generations.len());
+        assert_eq!(6, population.len());
+        for genome in population.iter() {
+            let mut names = genome.names();
+            names.sort();
+            names.dedup();
+            assert_eq!(Genome::STRATEGIES.len(), names.len());
+        }
+        assert_eq!((generations, population), run());
+    }
+}
@@ -0,0 +1,414 @@
+use super::{Coordinate, Grid, Playable, Player};
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+// A k-in-a-row board made of hexagons. Lines run along the three hex axes, and spaces are
+// named by axial coordinates (q, r). Under the hood it's an ordinary `Grid`, with axial
+// (q, r) stored at column q and row r, so every `Playable` works on it unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HexBoard {
+    shape: HexShape,
+    line_length: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexShape {
+    // A rhombus `size` hexes on a side, with q and r both running from 0 to size - 1
+    Rhombus(usize),
+
+    // A hexagon with `radius` rings around the centre hex at (0, 0)
+    Hexagon(usize),
+}
+
+impl HexBoard {
+    // Axial directions along the three axes
+    const DIRECTIONS: [(isize, isize); 3] = [(1, 0), (0, 1), (1, -1)];
+
+    pub fn new(shape: HexShape, line_length: usize) -> Self {
+        let board = Self { shape, line_length };
+        let span = board.span();
+        assert!(
+            span > 0 && span <= 26,
+            "Hex boards must be 1 to 26 hexes across"
+        );
+        // The longest lines run across the middle of the board
+        assert!(
+            line_length > 0 && line_length <= span,
+            "Lines must be between 1 and {} spaces long on this board",
+            span,
+        );
+        board
+    }
+
+    pub fn contains(&self, axial: &AxialCoordinate) -> bool {
+        match self.shape {
+            HexShape::Rhombus(size) => {
+                let size = size as isize;
+                axial.q >= 0 && axial.q < size && axial.r >= 0 && axial.r < size
+            }
+            HexShape::Hexagon(radius) => {
+                let radius = radius as isize;
+                axial.q.abs() <= radius
+                    && axial.r.abs() <= radius
+                    && (axial.q + axial.r).abs() <= radius
+            }
+        }
+    }
+
+    pub fn to_coordinate(&self, axial: &AxialCoordinate) -> Option<Coordinate> {
+        if self.contains(axial) {
+            let offset = self.offset();
+            Some(Coordinate::new(
+                (axial.q + offset) as usize,
+                (axial.r + offset) as usize,
+            ))
+        } else {
+            None
+        }
+    }
+
+    pub fn to_axial(&self, coordinate: &Coordinate) -> AxialCoordinate {
+        let offset = self.offset();
+        AxialCoordinate::new(
+            coordinate.x() as isize - offset,
+            coordinate.y() as isize - offset,
+        )
+    }
+
+    // Every hex on the board, row by row.
+    pub fn cells(&self) -> Vec<AxialCoordinate> {
+        let offset = self.offset();
+        let span = self.span() as isize;
+        (0..span)
+            .flat_map(|r| (0..span).map(move |q| AxialCoordinate::new(q - offset, r - offset)))
+            .filter(|axial| self.contains(axial))
+            .collect()
+    }
+
+    // An empty board, with the spaces that fall outside the hexagon blocked off.
+    pub fn grid(&self) -> Grid {
+        let mut lines = Vec::new();
+        for cell in self.cells() {
+            for (dq, dr) in Self::DIRECTIONS.iter() {
+                let line: Option<Vec<Coordinate>> = (0..self.line_length as isize)
+                    .map(|i| {
+                        self.to_coordinate(&AxialCoordinate::new(cell.q + dq * i, cell.r + dr * i))
+                    })
+                    .collect();
+                if let Some(line) = line {
+                    lines.push(line);
+                }
+            }
+        }
+
+        let mut grid = Grid::with_winning_sets(self.span(), self.span(), lines);
+        for coordinate in grid.coordinates().collect::<Vec<Coordinate>>() {
+            if !self.contains(&self.to_axial(&coordinate)) {
+                grid.block(&coordinate);
+            }
+        }
+        grid
+    }
+
+    // Draws the board with each row shifted half a hex from the one above, eg.
+    //
+    //      . .
+    //     . X .
+    //      O .
+    pub fn display<'a>(&'a self, grid: &'a Grid) -> HexDisplay<'a> {
+        HexDisplay { board: self, grid }
+    }
+
+    fn offset(&self) -> isize {
+        match self.shape {
+            HexShape::Rhombus(_) => 0,
+            HexShape::Hexagon(radius) => radius as isize,
+        }
+    }
+
+    fn span(&self) -> usize {
+        match self.shape {
+            HexShape::Rhombus(size) => size,
+            HexShape::Hexagon(radius) => radius * 2 + 1,
+        }
+    }
+}
+
+pub struct HexDisplay<'a> {
+    board: &'a HexBoard,
+    grid: &'a Grid,
+}
+
+impl<'a> fmt::Display for HexDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let offset = self.board.offset();
+
+        for y in 0..self.board.span() {
+            let r = y as isize - offset;
+            let indent = match self.board.shape {
+                HexShape::Rhombus(_) => y,
+                HexShape::Hexagon(_) => r.unsigned_abs(),
+            };
+
+            let cells: Vec<String> = (0..self.board.span())
+                .map(|x| Coordinate::new(x, y))
+                .filter(|c| self.board.contains(&self.board.to_axial(c)))
+                .map(|c| match self.grid.get_space(&c).get_player() {
+                    Some(player) => player.to_string(),
+                    None => ".".to_string(),
+                })
+                .collect();
+
+            writeln!(f, "{}{}", " ".repeat(indent), cells.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxialCoordinate {
+    q: isize,
+    r: isize,
+}
+
+impl AxialCoordinate {
+    pub fn new(q: isize, r: isize) -> Self {
+        Self { q, r }
+    }
+
+    pub fn q(&self) -> isize {
+        self.q
+    }
+
+    pub fn r(&self) -> isize {
+        self.r
+    }
+}
+
+impl FromStr for AxialCoordinate {
+    type Err = ParseAxialCoordinateError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let error = || ParseAxialCoordinateError(raw.to_string());
+        let trimmed = raw.trim();
+        let trimmed = trimmed
+            .strip_prefix('(')
+            .and_then(|t| t.strip_suffix(')'))
+            .unwrap_or(trimmed);
+
+        let mut parts = trimmed.splitn(2, ',');
+        match (parts.next(), parts.next()) {
+            (Some(q), Some(r)) => Ok(AxialCoordinate::new(
+                q.trim().parse().map_err(|_| error())?,
+                r.trim().parse().map_err(|_| error())?,
+            )),
+            _ => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for AxialCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{}", self.q, self.r)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseAxialCoordinateError(String);
+
+impl fmt::Display for ParseAxialCoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid coordinate: {} (expected format: q,r)", self.0)
+    }
+}
+
+// Takes moves from the keyboard as axial coordinates.
+pub struct HexHumanPlayer {
+    player: Player,
+    board: HexBoard,
+}
+
+impl HexHumanPlayer {
+    pub fn new(player: Player, board: HexBoard) -> Self {
+        Self { player, board }
+    }
+}
+
+impl Playable for HexHumanPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        loop {
+            println!("{}", self.board.display(grid));
+            println!("Enter {} move (q,r):", self.player);
+
+            let mut buf = String::new();
+            io::stdin().read_line(&mut buf).unwrap();
+
+            match buf.parse::<AxialCoordinate>() {
+                Ok(axial) => match self.board.to_coordinate(&axial) {
+                    Some(coordinate) if grid.is_legal(&coordinate) => break coordinate,
+                    _ => println!("{} is not a legal move.", axial),
+                },
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+}
+
+#[cfg(test)]
+mod test_hex {
+    use super::{AxialCoordinate, HexBoard, HexShape, ParseAxialCoordinateError};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Coordinate, Player};
+    use crate::rando::RandoPlayer;
+    use crate::{play_game, Playable, Reply, WinRule};
+
+    #[test]
+    fn rhombus_lines() {
+        let board = HexBoard::new(HexShape::Rhombus(3), 3);
+        let grid = board.grid();
+
+        // 3 along q, 3 along r, and the one long diagonal
+        assert_eq!(7, grid.lines().len());
+        assert_eq!(9, grid.legal_moves().len());
+    }
+
+    #[test]
+    fn hexagon_lines() {
+        let board = HexBoard::new(HexShape::Hexagon(1), 3);
+        let grid = board.grid();
+
+        assert_eq!(7, board.cells().len());
+        assert_eq!(7, grid.legal_moves().len());
+
+        // One through the centre along each axis
+        assert_eq!(3, grid.lines().len());
+        let centre = board.to_coordinate(&AxialCoordinate::new(0, 0)).unwrap();
+        assert_eq!(3, grid.lines_through(&centre).count());
+    }
+
+    #[test]
+    fn hexagon_corners_are_blocked() {
+        let board = HexBoard::new(HexShape::Hexagon(2), 3);
+        let grid = board.grid();
+
+        assert_eq!(19, grid.legal_moves().len());
+        assert!(!grid.is_legal(&Coordinate::new(0, 0)));
+        assert!(!grid.is_legal(&Coordinate::new(4, 4)));
+        assert!(grid.is_legal(&Coordinate::new(4, 0)));
+        assert_eq!(None, board.to_coordinate(&AxialCoordinate::new(-2, -2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Hex boards must be 1 to 26 hexes across")]
+    fn empty_rhombus() {
+        HexBoard::new(HexShape::Rhombus(0), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Hex boards must be 1 to 26 hexes across")]
+    fn hexagon_too_wide() {
+        HexBoard::new(HexShape::Hexagon(13), 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Lines must be between 1 and 5 spaces long on this board")]
+    fn lines_too_long() {
+        HexBoard::new(HexShape::Hexagon(2), 6);
+    }
+
+    #[test]
+    fn largest_boards() {
+        assert_eq!(26, HexBoard::new(HexShape::Rhombus(26), 26).grid().width());
+        assert_eq!(25, HexBoard::new(HexShape::Hexagon(12), 1).grid().width());
+    }
+
+    #[test]
+    fn swaps_centre_opening() {
+        let board = HexBoard::new(HexShape::Hexagon(2), 3);
+        let mut grid = board.grid();
+        let centre = board.to_coordinate(&AxialCoordinate::new(0, 0)).unwrap();
+        grid.set_space(&centre, &Player::X).unwrap();
+
+        let mut player = ComputerPlayer::new_silent(Player::O);
+        assert_eq!(Reply::Swap, player.reply_to_opening(&grid));
+    }
+
+    #[test]
+    fn coordinate_round_trip() {
+        let board = HexBoard::new(HexShape::Hexagon(2), 3);
+        for axial in board.cells() {
+            let coordinate = board.to_coordinate(&axial).unwrap();
+            assert_eq!(axial, board.to_axial(&coordinate));
+        }
+    }
+
+    #[test]
+    fn axial_from_str() {
+        assert_eq!(Ok(AxialCoordinate::new(2, -1)), "2,-1".parse());
+        assert_eq!(Ok(AxialCoordinate::new(0, 3)), " (0, 3)\n".parse());
+        assert_eq!(
+            Err(ParseAxialCoordinateError("A1".to_string())),
+            "A1".parse::<AxialCoordinate>(),
+        );
+        assert_eq!(
+            "Invalid coordinate: 1 (expected format: q,r)",
+            &format!("{}", "1".parse::<AxialCoordinate>().unwrap_err()),
+        );
+        assert_eq!("-1,0", &format!("{}", AxialCoordinate::new(-1, 0)));
+    }
+
+    #[test]
+    fn display() {
+        let board = HexBoard::new(HexShape::Hexagon(1), 3);
+        let mut grid = board.grid();
+        let centre = board.to_coordinate(&AxialCoordinate::new(0, 0)).unwrap();
+        let corner = board.to_coordinate(&AxialCoordinate::new(-1, 1)).unwrap();
+        grid.set_space(&centre, &Player::X).unwrap();
+        grid.set_space(&corner, &Player::O).unwrap();
+
+        assert_eq!(" . .\n. X .\n O .\n", format!("{}", board.display(&grid)));
+
+        let board = HexBoard::new(HexShape::Rhombus(2), 2);
+        assert_eq!(". .\n . .\n", format!("{}", board.display(&board.grid())));
+    }
+
+    #[test]
+    fn computer_blocks_along_hex_axis() {
+        // O holds two of the three hexes on the (1, -1) axis through the centre.
+        let board = HexBoard::new(HexShape::Hexagon(1), 3);
+        let mut grid = board.grid();
+        let hex = |q, r| board.to_coordinate(&AxialCoordinate::new(q, r)).unwrap();
+        grid.set_space(&hex(-1, 1), &Player::O).unwrap();
+        grid.set_space(&hex(0, 0), &Player::O).unwrap();
+        grid.set_space(&hex(0, -1), &Player::X).unwrap();
+
+        let mut player = ComputerPlayer::new_silent(Player::X);
+        assert_eq!(hex(1, -1), player.play(&grid));
+    }
+
+    #[test]
+    fn plays_on_hex_board() {
+        let board = HexBoard::new(HexShape::Hexagon(3), 4);
+        let standings = play_game(
+            board.grid(),
+            vec![
+                (Player::X, Box::new(ComputerPlayer::new_silent(Player::X))),
+                (Player::O, Box::new(RandoPlayer::new())),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        let grid = standings.get_grid();
+        for coordinate in grid.coordinates() {
+            if !board.contains(&board.to_axial(&coordinate)) {
+                assert_eq!(None, grid.get_space(&coordinate).get_player());
+            }
+        }
+    }
+}
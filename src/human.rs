@@ -1,4 +1,4 @@
-use super::{Coordinate, Grid, Playable, Player};
+use super::{Coordinate, Grid, Playable, Player, Reply};
 use std::io;
 
 pub struct HumanPlayer {
@@ -15,7 +15,7 @@ impl Playable for HumanPlayer {
     fn play(&mut self, grid: &Grid) -> Coordinate {
         loop {
             println!("{}", grid);
-            println!("");
+            println!();
             println!("Enter {} move:", self.player);
 
             let mut buf = String::new();
@@ -31,4 +31,59 @@ impl Playable for HumanPlayer {
             };
         }
     }
+
+    // Takes all of a turn's stones on one line, eg. "K10 L11".
+    fn play_turn(&mut self, grid: &Grid, player: &Player, stones: usize) -> Vec<Coordinate> {
+        if stones == 1 {
+            return vec![self.play(grid)];
+        }
+
+        loop {
+            println!("{}", grid);
+            println!();
+            println!("Enter {} {} moves, separated by spaces:", stones, player);
+
+            let mut buf = String::new();
+            io::stdin().read_line(&mut buf).unwrap();
+
+            let mut next_grid = grid.clone();
+            let coordinates: Result<Vec<Coordinate>, String> = buf
+                .split_whitespace()
+                .map(|raw| {
+                    let coordinate = raw.parse().map_err(|e| format!("{}", e))?;
+                    next_grid
+                        .set_space(&coordinate, player)
+                        .map_err(|e| format!("{}", e))?;
+                    Ok(coordinate)
+                })
+                .collect();
+
+            match coordinates {
+                Ok(coordinates) if coordinates.len() == stones => break coordinates,
+                Ok(_) => println!("Please enter exactly {} moves.", stones),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+
+    fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+        loop {
+            println!("{}", grid);
+            println!();
+            println!("Swap sides and take over {}? (y/n)", self.player.turn());
+
+            let mut buf = String::new();
+            io::stdin().read_line(&mut buf).unwrap();
+
+            match buf.trim() {
+                "y" | "Y" => break Reply::Swap,
+                "n" | "N" => break Reply::Play(self.play(grid)),
+                _ => println!("Please answer y or n."),
+            }
+        }
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
 }
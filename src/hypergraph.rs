@@ -0,0 +1,197 @@
+use super::file::FileError;
+use super::{Coordinate, Grid};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// A board described by its winning sets, as read from a text file. Each line lists the
+// spaces in one set, eg.
+//
+//     # The diagonals of a 3x3 board
+//     size 3x3
+//     A1 B2 C3
+//     A3 B2 C1
+//
+// The `size` line is optional; without it the board is just big enough to hold every set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hypergraph {
+    width: usize,
+    height: usize,
+    sets: Vec<Vec<Coordinate>>,
+}
+
+impl Hypergraph {
+    pub fn new(width: usize, height: usize, sets: Vec<Vec<Coordinate>>) -> Self {
+        Self {
+            width,
+            height,
+            sets,
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn get_sets(&self) -> &[Vec<Coordinate>] {
+        &self.sets
+    }
+
+    // An empty board played on this hypergraph.
+    pub fn grid(&self) -> Grid {
+        Grid::with_winning_sets(self.width, self.height, self.sets.clone())
+    }
+}
+
+impl FromStr for Hypergraph {
+    type Err = FileError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut size = None;
+        let mut sets = Vec::new();
+
+        for (number, line) in raw.lines().enumerate() {
+            let error =
+                |message: &str| FileError::Parse("hypergraph", number + 1, message.to_string());
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if let Some(dimensions) = line.strip_prefix("size ") {
+                let mut parts = dimensions.trim().splitn(2, 'x');
+                match (
+                    parts.next().and_then(|w| w.parse::<usize>().ok()),
+                    parts.next().and_then(|h| h.parse::<usize>().ok()),
+                ) {
+                    (Some(width), Some(height)) if width > 0 && width <= 26 && height > 0 => {
+                        size = Some((width, height))
+                    }
+                    _ => return Err(error("expected a size like 3x3, up to 26 wide")),
+                }
+            } else {
+                let set = line
+                    .split_whitespace()
+                    .map(|raw| raw.parse().map_err(|e| error(&format!("{}", e))))
+                    .collect::<Result<Vec<Coordinate>, FileError>>()?;
+                sets.push(set);
+            }
+        }
+
+        if sets.is_empty() {
+            return Err(FileError::Parse(
+                "hypergraph",
+                0,
+                "no winning sets".to_string(),
+            ));
+        }
+
+        let (width, height) = match size {
+            Some((width, height)) => {
+                if sets
+                    .iter()
+                    .flatten()
+                    .any(|c| c.x() >= width || c.y() >= height)
+                {
+                    return Err(FileError::Parse(
+                        "hypergraph",
+                        0,
+                        "a winning set runs off the board".to_string(),
+                    ));
+                }
+                (width, height)
+            }
+            None => (
+                sets.iter().flatten().map(|c| c.x() + 1).max().unwrap(),
+                sets.iter().flatten().map(|c| c.y() + 1).max().unwrap(),
+            ),
+        };
+
+        Ok(Hypergraph::new(width, height, sets))
+    }
+}
+
+#[cfg(test)]
+mod test_hypergraph {
+    use super::{FileError, Hypergraph};
+    use crate::game::{Coordinate, Player};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn from_str() {
+        let hypergraph: Hypergraph = "
+            # Diagonals only
+            A1 B2 C3
+
+            a3 b2 c1
+        "
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            Hypergraph::new(
+                3,
+                3,
+                vec![
+                    vec![
+                        Coordinate::new(0, 0),
+                        Coordinate::new(1, 1),
+                        Coordinate::new(2, 2)
+                    ],
+                    vec![
+                        Coordinate::new(0, 2),
+                        Coordinate::new(1, 1),
+                        Coordinate::new(2, 0)
+                    ],
+                ],
+            ),
+            hypergraph,
+        );
+    }
+
+    #[test]
+    fn from_str_with_size() {
+        let hypergraph: Hypergraph = "size 5x4\nA1 B1\nD4".parse().unwrap();
+        let grid = hypergraph.grid();
+        assert_eq!(5, grid.width());
+        assert_eq!(4, grid.height());
+        assert_eq!(2, grid.lines().len());
+    }
+
+    #[test]
+    fn from_str_invalid() {
+        let message = |raw: &str| format!("{}", raw.parse::<Hypergraph>().unwrap_err());
+
+        assert_eq!("Invalid hypergraph: no winning sets", message("# nothing"));
+        assert_eq!(
+            "Invalid hypergraph on line 2: Invalid coordinate: B (expected format: A1)",
+            message("A1 B1\nA2 B"),
+        );
+        assert_eq!(
+            "Invalid hypergraph on line 1: expected a size like 3x3, up to 26 wide",
+            message("size 3by3\nA1"),
+        );
+        assert_eq!(
+            "Invalid hypergraph: a winning set runs off the board",
+            message("size 2x2\nA1 C1"),
+        );
+    }
+
+    #[test]
+    fn load() {
+        let path = env::temp_dir().join(format!("hypergraph-{}.txt", std::process::id()));
+        fs::write(&path, "A1 B1 C1\nA1 A2\n").unwrap();
+        let hypergraph = Hypergraph::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mut grid = hypergraph.unwrap().grid();
+        grid.set_space(&Coordinate::new(0, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        assert_eq!(Some(Player::O), grid.get_winner());
+
+        match Hypergraph::load(env::temp_dir().join("no-such-hypergraph.txt")) {
+            Err(FileError::Io(_)) => {}
+            other => panic!("Expected an IO error, got {:?}", other),
+        }
+    }
+}
@@ -1,12 +1,36 @@
-mod computer;
-mod game;
-mod human;
-mod rando;
+pub mod alpha_beta;
+pub mod book;
+pub mod computer;
+pub mod file;
+pub mod game;
+pub mod genetic;
+pub mod hex;
+pub mod human;
+pub mod hypergraph;
+pub mod maker_breaker;
+pub mod mcts;
+pub mod menace;
+pub mod neural;
+pub mod phantom;
+pub mod pie;
+pub mod q_learning;
+pub mod rando;
+pub mod random_turn;
+pub mod rules;
+pub mod solver;
+pub mod stochastic;
+pub mod strategy;
+pub mod symmetry;
+pub mod teeko;
+pub mod trap;
+pub mod validity;
 
 use computer::ComputerPlayer;
 use game::{Coordinate, Grid, Player};
-use human::HumanPlayer;
-use rando::RandoPlayer;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rules::{Agent, KInARow, Position, Record, Rules};
+use std::fmt;
 
 pub fn run() {
     // Ready player one
@@ -17,7 +41,7 @@ pub fn run() {
 
     let final_grid = play(Box::new(player_x), Box::new(player_o));
 
-    println!("");
+    println!();
 
     if let Some(winner) = final_grid.get_winner() {
         println!("{} wins!", winner);
@@ -25,37 +49,522 @@ pub fn run() {
         println!("The game ended in a draw!");
     }
 
-    println!("");
+    println!();
     println!("{}", final_grid);
 }
 
-fn play(mut player_x: Box<dyn Playable>, mut player_o: Box<dyn Playable>) -> Grid {
-    let mut grid = Grid::empty();
+pub fn play(player_x: Box<dyn Playable>, player_o: Box<dyn Playable>) -> Grid {
+    rules::play(
+        &KInARow::classic(),
+        vec![
+            (Player::X, Box::new(player_x)),
+            (Player::O, Box::new(player_o)),
+        ],
+    )
+    .get_state()
+    .get_grid()
+    .clone()
+}
 
-    let mut current_player = Player::X;
+// Like `play()`, but with both players reseeded from `seed` first, so the same players
+// and seed always replay the same game.
+pub fn play_seeded(
+    mut player_x: Box<dyn Playable>,
+    mut player_o: Box<dyn Playable>,
+    seed: u64,
+) -> Grid {
+    let mut rng = StdRng::seed_from_u64(seed);
+    player_x.reseed(rng.gen());
+    player_o.reseed(rng.gen());
+    play(player_x, player_o)
+}
 
-    while grid.is_in_progress() {
-        let coordinate = match current_player {
-            Player::X => player_x.play(&grid),
-            Player::O => player_o.play(&grid),
-        };
+// Gives each seat its own seed, drawn in seating order from one game seed.
+pub fn reseed_seats(seats: &mut [(Player, Box<dyn Playable>)], seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for (_, playable) in seats.iter_mut() {
+        playable.reseed(rng.gen());
+    }
+}
 
-        grid.set_space(&coordinate, &current_player)
-            .expect("Illegal move!");
+// Plays `games` games on copies of `grid`, asking `seats` for the seats of each game by
+// its number. Every game is seeded from the match seed, so a whole match, or a
+// tournament whose pairings change from game to game, replays exactly from one seed.
+pub fn play_match<F>(
+    grid: &Grid,
+    win_rule: WinRule,
+    games: usize,
+    seed: u64,
+    mut seats: F,
+) -> Vec<Standings>
+where
+    F: FnMut(usize) -> Vec<(Player, Box<dyn Playable>)>,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..games)
+        .map(|game| {
+            let mut seats = seats(game);
+            reseed_seats(&mut seats, rng.gen());
+            play_game(grid.clone(), seats, win_rule)
+        })
+        .collect()
+}
 
-        current_player = current_player.turn();
-    }
+// Plays out a game between any number of seats, each claiming its own symbol. Seats move
+// in the order given, which is how alternative turn orders are set up.
+pub fn play_game(
+    grid: Grid,
+    seats: Vec<(Player, Box<dyn Playable>)>,
+    win_rule: WinRule,
+) -> Standings {
+    play_turns(grid, seats, win_rule, TurnStructure::SINGLE)
+}
+
+// Like `play_game()`, but each turn places as many stones as the turn structure calls for.
+// A turn ends early if one of its stones wins the game.
+pub fn play_turns(
+    grid: Grid,
+    seats: Vec<(Player, Box<dyn Playable>)>,
+    win_rule: WinRule,
+    structure: TurnStructure,
+) -> Standings {
+    let rules = KInARow::new(grid, seats.iter().map(|(player, _)| *player).collect())
+        .with_win_rule(win_rule)
+        .with_turns(structure);
+    let seats = seats
+        .into_iter()
+        .map(|(player, playable)| {
+            let seat: Box<dyn Agent<KInARow>> = Box::new(TurnSeat {
+                playable,
+                stones: Vec::new(),
+            });
+            (player, seat)
+        })
+        .collect();
+
+    Standings::new(&rules, &rules::play(&rules, seats))
+}
 
-    grid
+// Seats a `Playable` in a game whose turns can place several stones. The player picks a
+// whole turn's stones at once, and they're handed to the game one at a time.
+struct TurnSeat {
+    playable: Box<dyn Playable>,
+    stones: Vec<Coordinate>,
 }
 
-trait Playable {
+impl Agent<KInARow> for TurnSeat {
+    fn play(&mut self, _rules: &KInARow, state: &Position) -> Coordinate {
+        // Anything left over from a turn that a win cut short is never played.
+        if self.stones.len() != state.get_stones() {
+            let player = state.get_player();
+            let stones = state.get_stones();
+            self.stones = self.playable.play_turn(state.get_grid(), &player, stones);
+            assert_eq!(
+                stones,
+                self.stones.len(),
+                "{} should place {} stones this turn",
+                player,
+                stones,
+            );
+            self.stones.reverse();
+        }
+        self.stones.pop().unwrap()
+    }
+}
+
+pub trait Playable {
     fn play(&mut self, grid: &Grid) -> Coordinate;
+
+    // Picks the stones for a turn that places more than one. By default they're chosen one
+    // at a time, each seeing the ones before it already on the board.
+    fn play_turn(&mut self, grid: &Grid, player: &Player, stones: usize) -> Vec<Coordinate> {
+        let mut grid = grid.clone();
+        let mut coordinates = Vec::new();
+
+        for _ in 0..stones {
+            let coordinate = self.play(&grid);
+            grid.set_space(&coordinate, player).expect("Illegal move!");
+            coordinates.push(coordinate);
+        }
+
+        coordinates
+    }
+
+    // Under the swap rule, the second player is offered X's opening stone instead of
+    // replying to it.
+    fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+        Reply::Play(self.play(grid))
+    }
+
+    // Called when the seat ends up playing a different symbol, eg. after a swap.
+    fn switch_sides(&mut self, _player: Player) {}
+
+    // Restarts any random choices the player makes from `seed`, so that a seeded game
+    // replays exactly. Players that never choose at random can ignore it.
+    fn reseed(&mut self, _seed: u64) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    Swap,
+    Play(Coordinate),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinRule {
+    // The first player to complete a line wins outright.
+    FirstToLine,
+
+    // Completing a line takes the next place and leaves the rotation, and the remaining
+    // players play on for the places behind.
+    Elimination,
+}
+
+// How many stones each turn places: `first` on the opening turn and `rest` on every turn
+// after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TurnStructure {
+    first: usize,
+    rest: usize,
+}
+
+impl TurnStructure {
+    // One stone a turn, as in tic-tac-toe
+    pub const SINGLE: TurnStructure = TurnStructure { first: 1, rest: 1 };
+
+    // One stone on the opening turn and two after that, as in Connect6
+    pub const CONNECT6: TurnStructure = TurnStructure { first: 1, rest: 2 };
+
+    pub fn new(first: usize, rest: usize) -> Self {
+        assert!(
+            first > 0 && rest > 0,
+            "Every turn must place at least one stone",
+        );
+        Self { first, rest }
+    }
+
+    // The number of stones placed on a turn, counting from 0.
+    pub fn stones(&self, turn: usize) -> usize {
+        if turn == 0 {
+            self.first
+        } else {
+            self.rest
+        }
+    }
+}
+
+// The stones one player placed in a single turn, written together, eg. "O K10 L11". Any
+// stones a placement captured follow it after an "x", eg. "X K10xL11xM12".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Turn {
+    player: Player,
+    placements: Vec<Coordinate>,
+    captures: Vec<Vec<Coordinate>>,
+}
+
+impl Turn {
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_placements(&self) -> &[Coordinate] {
+        &self.placements
+    }
+
+    // The stones each placement captured, in the same order as the placements.
+    pub fn get_captures(&self) -> &[Vec<Coordinate>] {
+        &self.captures
+    }
+}
+
+impl fmt::Display for Turn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.player)?;
+        for (coordinate, captures) in self.placements.iter().zip(self.captures.iter()) {
+            write!(f, " {}", coordinate)?;
+            for captured in captures {
+                write!(f, "x{}", captured)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standings {
+    grid: Grid,
+    placings: Vec<Player>,
+    turns: Vec<Turn>,
+}
+
+impl Standings {
+    // Replays a game's moves to group them into turns and find what each one captured.
+    // Play never passes from a player back to themselves while anyone else is still in,
+    // so a turn is every move in a row by the same player.
+    fn new(rules: &KInARow, record: &Record<KInARow>) -> Self {
+        let mut grid = rules.initial_state().get_grid().clone();
+        let mut turns: Vec<Turn> = Vec::new();
+
+        for (player, coordinate) in record.get_moves() {
+            let captures = grid.captures_with(coordinate, player);
+            grid.set_space(coordinate, player).expect("Illegal move!");
+
+            match turns.last_mut() {
+                Some(turn) if turn.player == *player => {
+                    turn.placements.push(*coordinate);
+                    turn.captures.push(captures);
+                }
+                _ => turns.push(Turn {
+                    player: *player,
+                    placements: vec![*coordinate],
+                    captures: vec![captures],
+                }),
+            }
+        }
+
+        Standings {
+            grid,
+            placings: record.get_state().get_placings().to_vec(),
+            turns,
+        }
+    }
+
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // Players in the order they finished. Anyone left out when the board filled up shares
+    // the remaining places.
+    pub fn get_placings(&self) -> &[Player] {
+        &self.placings
+    }
+
+    pub fn get_winner(&self) -> Option<Player> {
+        self.placings.first().copied()
+    }
+
+    // Every turn of the game, in order.
+    pub fn get_turns(&self) -> &[Turn] {
+        &self.turns
+    }
+
+    // The game record, one numbered turn per line, eg.
+    //
+    //     1. X J10
+    //     2. O K10 K11
+    pub fn notation(&self) -> String {
+        self.turns
+            .iter()
+            .enumerate()
+            .map(|(i, turn)| format!("{}. {}\n", i + 1, turn))
+            .collect()
+    }
 }
 
 #[cfg(test)]
 mod test_play {
     use super::*;
+    use computer::Difficulty;
+    use game::Space;
+    use rando::RandoPlayer;
+
+    struct ScriptedPlayer(Vec<Coordinate>);
+
+    impl Playable for ScriptedPlayer {
+        fn play(&mut self, _grid: &Grid) -> Coordinate {
+            self.0.remove(0)
+        }
+    }
+
+    fn scripted(moves: &[&str]) -> Box<dyn Playable> {
+        Box::new(ScriptedPlayer(
+            moves.iter().map(|m| m.parse().unwrap()).collect(),
+        ))
+    }
+
+    #[test]
+    fn first_to_line() {
+        let standings = play_game(
+            Grid::with_size(4, 4, 3),
+            vec![
+                (Player::X, scripted(&["A1", "A2", "A3"])),
+                (Player::O, scripted(&["B1", "B2"])),
+                (Player::Delta, scripted(&["C1", "C2"])),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        assert_eq!(Some(Player::X), standings.get_winner());
+        assert_eq!(&[Player::X], standings.get_placings());
+        assert_eq!(
+            Space::Empty,
+            standings.get_grid().get_space(&"B3".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn turn_order() {
+        let standings = play_game(
+            Grid::with_size(4, 4, 3),
+            vec![
+                (Player::Square, scripted(&["A1", "A2", "A3"])),
+                (Player::Delta, scripted(&["B1", "B2", "B3"])),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        assert_eq!(Some(Player::Square), standings.get_winner());
+        assert_eq!(3, standings.get_grid().count(&Player::Square));
+        assert_eq!(2, standings.get_grid().count(&Player::Delta));
+    }
+
+    #[test]
+    fn elimination() {
+        let standings = play_game(
+            Grid::with_size(4, 4, 3),
+            vec![
+                (Player::X, scripted(&["A1", "A2", "A3"])),
+                (Player::O, scripted(&["B1", "B2", "D1", "B3"])),
+                (Player::Delta, scripted(&["C1", "C2", "D2", "C3"])),
+            ],
+            WinRule::Elimination,
+        );
+
+        assert_eq!(
+            &[Player::X, Player::O, Player::Delta],
+            standings.get_placings(),
+        );
+        assert_eq!(Some(Player::X), standings.get_winner());
+    }
+
+    #[test]
+    fn four_player_game() {
+        let standings = play_game(
+            Grid::with_size(6, 6, 4),
+            vec![
+                (Player::X, Box::new(RandoPlayer::new())),
+                (Player::O, Box::new(ComputerPlayer::new_silent(Player::O))),
+                (Player::Delta, Box::new(RandoPlayer::new())),
+                (
+                    Player::Square,
+                    Box::new(ComputerPlayer::new_silent(Player::Square)),
+                ),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        // Seats take turns in order, so nobody can be more than one move ahead.
+        let grid = standings.get_grid();
+        let counts: Vec<usize> = Player::ALL.iter().map(|p| grid.count(p)).collect();
+        assert!(counts.windows(2).all(|w| w[0] >= w[1]));
+        assert!(counts[0] - counts[3] <= 1);
+
+        if let Some(winner) = standings.get_winner() {
+            assert!(grid.has_line(&winner));
+        } else {
+            assert!(!grid.has_legal_moves());
+        }
+    }
+
+    #[test]
+    fn connect6_turns() {
+        let standings = play_turns(
+            Grid::with_size(8, 8, 4),
+            vec![
+                (Player::X, scripted(&["A1", "A2", "A3", "A4", "H8"])),
+                (Player::O, scripted(&["B1", "B2", "C1", "C2"])),
+            ],
+            WinRule::FirstToLine,
+            TurnStructure::CONNECT6,
+        );
+
+        assert_eq!(Some(Player::X), standings.get_winner());
+        let placements: Vec<usize> = standings
+            .get_turns()
+            .iter()
+            .map(|turn| turn.get_placements().len())
+            .collect();
+        assert_eq!(vec![1, 2, 2, 2, 1], placements);
+
+        // X's fourth stone completes the line, so H8 is never placed.
+        assert_eq!(
+            "1. X A1\n2. O B1 B2\n3. X A2 A3\n4. O C1 C2\n5. X A4\n",
+            standings.notation(),
+        );
+    }
+
+    #[test]
+    fn capture_notation() {
+        // X brackets the O pair on B1 and C1, and O plays back into the gap.
+        let standings = play_game(
+            Grid::with_size(5, 1, 4).with_captures(),
+            vec![
+                (Player::X, scripted(&["A1", "E1", "D1", "C1"])),
+                (Player::O, scripted(&["B1", "C1", "B1"])),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        assert_eq!(1, standings.get_grid().captured(&Player::X));
+        assert_eq!(None, standings.get_winner());
+        assert_eq!(
+            &[vec![Coordinate::new(2, 0), Coordinate::new(1, 0)]],
+            standings.get_turns()[4].get_captures(),
+        );
+        assert_eq!(
+            "1. X A1\n2. O B1\n3. X E1\n4. O C1\n5. X D1xC1xB1\n6. O B1\n7. X C1\n",
+            standings.notation(),
+        );
+    }
+
+    #[test]
+    fn turn_structure() {
+        assert_eq!(1, TurnStructure::CONNECT6.stones(0));
+        assert_eq!(2, TurnStructure::CONNECT6.stones(1));
+        assert_eq!(2, TurnStructure::CONNECT6.stones(7));
+        assert_eq!(TurnStructure::SINGLE, TurnStructure::new(1, 1));
+        assert_eq!(3, TurnStructure::new(2, 3).stones(4));
+    }
+
+    #[test]
+    fn single_stone_turns() {
+        let standings = play_game(
+            Grid::empty(),
+            vec![
+                (Player::X, scripted(&["A1", "A2", "A3"])),
+                (Player::O, scripted(&["B1", "B2"])),
+            ],
+            WinRule::FirstToLine,
+        );
+
+        assert_eq!(5, standings.get_turns().len());
+        assert_eq!(Player::O, standings.get_turns()[1].get_player());
+        assert_eq!("O B1", &format!("{}", standings.get_turns()[1]));
+    }
+
+    #[test]
+    fn connect6_fills_the_board() {
+        // Nobody can win a board without any winning sets, and the last turn only has one
+        // space left.
+        let standings = play_turns(
+            Grid::with_winning_sets(6, 4, Vec::new()),
+            vec![
+                (Player::X, Box::new(RandoPlayer::new())),
+                (Player::O, Box::new(ComputerPlayer::new_silent(Player::O))),
+            ],
+            WinRule::FirstToLine,
+            TurnStructure::CONNECT6,
+        );
+
+        let grid = standings.get_grid();
+        assert!(!grid.has_legal_moves());
+        assert_eq!(12, grid.count(&Player::X));
+        assert_eq!(12, grid.count(&Player::O));
+        assert_eq!(
+            1,
+            standings.get_turns().last().unwrap().get_placements().len()
+        );
+    }
 
     #[test]
     #[ignore]
@@ -73,6 +582,7 @@ mod test_play {
             match grid.get_winner() {
                 Some(Player::X) => x_wins += 1,
                 Some(Player::O) => o_wins += 1,
+                Some(_) => unreachable!(),
                 None => draws += 1,
             }
         }
@@ -100,6 +610,7 @@ mod test_play {
             match grid.get_winner() {
                 Some(Player::X) => x_wins += 1,
                 Some(Player::O) => o_wins += 1,
+                Some(_) => unreachable!(),
                 None => draws += 1,
             }
         }
@@ -110,4 +621,119 @@ mod test_play {
             o_wins, x_wins, draws
         );
     }
+
+    #[test]
+    fn perfect_computer_never_loses() {
+        for seed in 0..20 {
+            let grid = play_seeded(
+                Box::new(
+                    ComputerPlayer::new_silent(Player::X).with_difficulty(Difficulty::Perfect),
+                ),
+                Box::new(RandoPlayer::new()),
+                seed,
+            );
+            assert_ne!(Some(Player::O), grid.get_winner());
+
+            let grid = play_seeded(
+                Box::new(RandoPlayer::new()),
+                Box::new(
+                    ComputerPlayer::new_silent(Player::O).with_difficulty(Difficulty::Perfect),
+                ),
+                seed,
+            );
+            assert_ne!(Some(Player::X), grid.get_winner());
+        }
+    }
+
+    #[test]
+    fn seeded_games_replay() {
+        let game = |seed| {
+            play_seeded(
+                Box::new(ComputerPlayer::new_silent(Player::X).with_difficulty(Difficulty::Easy)),
+                Box::new(RandoPlayer::new()),
+                seed,
+            )
+        };
+
+        assert_eq!(game(1), game(1));
+        assert_ne!(game(1), game(2));
+    }
+
+    #[test]
+    fn seeded_matches_replay() {
+        // A small round robin: each pairing plays from both sides.
+        let contestants = [Difficulty::Beginner, Difficulty::Easy, Difficulty::Hard];
+        let tournament = |seed| {
+            play_match(&Grid::empty(), WinRule::FirstToLine, 18, seed, |game| {
+                let pairing = game % 6;
+                let first = contestants[pairing / 2];
+                let second = contestants[(pairing / 2 + 1 + pairing % 2) % 3];
+                vec![
+                    (
+                        Player::X,
+                        Box::new(ComputerPlayer::new_silent(Player::X).with_difficulty(first))
+                            as Box<dyn Playable>,
+                    ),
+                    (
+                        Player::O,
+                        Box::new(ComputerPlayer::new_silent(Player::O).with_difficulty(second)),
+                    ),
+                ]
+            })
+        };
+
+        let first = tournament(7);
+        assert_eq!(18, first.len());
+        assert_eq!(first, tournament(7));
+        assert_ne!(first, tournament(8));
+    }
+
+    // Prints how each difficulty level fares against a random player, from both sides.
+    #[test]
+    #[ignore]
+    fn difficulty_levels() {
+        for difficulty in Difficulty::ALL.iter() {
+            let mut results = [[0; 3]; 2];
+
+            for seed in 0..1000 {
+                let grids = [
+                    play_seeded(
+                        Box::new(
+                            ComputerPlayer::new_silent(Player::X).with_difficulty(*difficulty),
+                        ),
+                        Box::new(RandoPlayer::new()),
+                        seed,
+                    ),
+                    play_seeded(
+                        Box::new(RandoPlayer::new()),
+                        Box::new(
+                            ComputerPlayer::new_silent(Player::O).with_difficulty(*difficulty),
+                        ),
+                        seed,
+                    ),
+                ];
+
+                for (side, (grid, player)) in
+                    grids.iter().zip([Player::X, Player::O].iter()).enumerate()
+                {
+                    results[side][match grid.get_winner() {
+                        Some(winner) if winner == *player => 0,
+                        None => 1,
+                        Some(_) => 2,
+                    }] += 1;
+                }
+            }
+
+            println!(
+                "{}: as X {}/{}/{}, as O {}/{}/{} (wins/draws/losses)",
+                difficulty,
+                results[0][0],
+                results[0][1],
+                results[0][2],
+                results[1][0],
+                results[1][1],
+                results[1][2],
+            );
+        }
+    }
 }
@@ -0,0 +1,225 @@
+use super::rules::{self, Agent, Position, Rules};
+use super::{Coordinate, Grid, Playable, Player};
+use std::fmt;
+
+// In a Maker-Breaker game the two sides want different things: Maker (playing X) wins by
+// claiming every space in any one winning set, and Breaker (playing O) wins by putting a
+// mark in every set so that none can be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Maker,
+    Breaker,
+}
+
+impl Role {
+    pub fn get_player(&self) -> Player {
+        match self {
+            Role::Maker => Player::X,
+            Role::Breaker => Player::O,
+        }
+    }
+
+    pub fn other(&self) -> Role {
+        match self {
+            Role::Maker => Role::Breaker,
+            Role::Breaker => Role::Maker,
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::Maker => write!(f, "Maker"),
+            Role::Breaker => write!(f, "Breaker"),
+        }
+    }
+}
+
+// Who has won, if anyone. Once the board is full, one side or the other always has.
+pub fn get_winner(grid: &Grid) -> Option<Role> {
+    let maker = Some(Role::Maker.get_player());
+
+    if grid
+        .lines()
+        .any(|line| line.get_spaces().iter().all(|s| s.get_player() == maker))
+    {
+        Some(Role::Maker)
+    } else if grid.lines().all(|line| {
+        line.get_spaces()
+            .iter()
+            .any(|s| s.get_player().is_some() && s.get_player() != maker)
+    }) {
+        Some(Role::Breaker)
+    } else {
+        None
+    }
+}
+
+// A Maker-Breaker game under the generic rules, with either side moving first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MakerBreaker {
+    grid: Grid,
+    first: Role,
+}
+
+impl MakerBreaker {
+    pub fn new(grid: Grid, first: Role) -> Self {
+        Self { grid, first }
+    }
+}
+
+impl Rules for MakerBreaker {
+    type State = Position;
+    type Move = Coordinate;
+    type Outcome = Role;
+
+    fn initial_state(&self) -> Position {
+        Position::new(self.grid.clone(), self.first.get_player())
+    }
+
+    fn to_move(&self, state: &Position) -> Player {
+        state.get_player()
+    }
+
+    fn legal_moves(&self, state: &Position) -> Vec<Coordinate> {
+        state.get_grid().legal_moves()
+    }
+
+    fn apply(&self, state: &Position, chosen: &Coordinate) -> Position {
+        let player = state.get_player();
+        let mut grid = state.get_grid().clone();
+        grid.set_space(chosen, &player).expect("Illegal move!");
+        state.next(grid, player.turn())
+    }
+
+    fn outcome(&self, state: &Position) -> Option<Role> {
+        get_winner(state.get_grid())
+    }
+
+    // Somebody always wins, so there are no draws.
+    fn payoff(&self, winner: &Role, player: &Player) -> f64 {
+        if winner.get_player() == *player {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+// Lets any `Playable` take either side.
+impl Agent<MakerBreaker> for Box<dyn Playable> {
+    fn play(&mut self, _rules: &MakerBreaker, state: &Position) -> Coordinate {
+        Playable::play(self.as_mut(), state.get_grid())
+    }
+}
+
+pub fn play(
+    grid: Grid,
+    maker: Box<dyn Playable>,
+    breaker: Box<dyn Playable>,
+    first: Role,
+) -> MakerBreakerResult {
+    let record = rules::play(
+        &MakerBreaker::new(grid, first),
+        vec![
+            (Role::Maker.get_player(), Box::new(maker)),
+            (Role::Breaker.get_player(), Box::new(breaker)),
+        ],
+    );
+
+    MakerBreakerResult {
+        grid: record.get_state().get_grid().clone(),
+        winner: *record.get_outcome(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MakerBreakerResult {
+    grid: Grid,
+    winner: Role,
+}
+
+impl MakerBreakerResult {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn get_winner(&self) -> Role {
+        self.winner
+    }
+}
+
+#[cfg(test)]
+mod test_maker_breaker {
+    use super::{get_winner, play, Role};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Coordinate, Grid, Player, Space};
+    use crate::hypergraph::Hypergraph;
+    use crate::rando::RandoPlayer;
+    use crate::Playable;
+
+    struct ScriptedPlayer(Vec<Coordinate>);
+
+    impl Playable for ScriptedPlayer {
+        fn play(&mut self, _grid: &Grid) -> Coordinate {
+            self.0.remove(0)
+        }
+    }
+
+    fn scripted(moves: &[&str]) -> Box<dyn Playable> {
+        Box::new(ScriptedPlayer(
+            moves.iter().map(|m| m.parse().unwrap()).collect(),
+        ))
+    }
+
+    #[test]
+    fn maker_claims_a_set() {
+        let grid = Grid::new([
+            [Space::X, Space::O, Space::Empty],
+            [Space::Empty, Space::X, Space::O],
+            [Space::Empty, Space::Empty, Space::X],
+        ]);
+        assert_eq!(Some(Role::Maker), get_winner(&grid));
+    }
+
+    #[test]
+    fn breaker_blocks_every_set() {
+        let hypergraph: Hypergraph = "A1 B1\nB1 C1\nA2 B2".parse().unwrap();
+        let mut grid = hypergraph.grid();
+        grid.set_space(&Coordinate::new(1, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::X).unwrap();
+        assert_eq!(None, get_winner(&grid));
+
+        // Breaker doesn't need a set of its own, just a mark in each one.
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+        assert_eq!(Some(Role::Breaker), get_winner(&grid));
+    }
+
+    #[test]
+    fn breaker_can_move_first() {
+        let hypergraph: Hypergraph = "A1 B1\nA2 B2".parse().unwrap();
+        let result = play(
+            hypergraph.grid(),
+            scripted(&["A2"]),
+            scripted(&["A1", "B2"]),
+            Role::Breaker,
+        );
+        assert_eq!(Role::Breaker, result.get_winner());
+        assert_eq!(2, result.get_grid().count(&Player::O));
+        assert_eq!(1, result.get_grid().count(&Player::X));
+    }
+
+    #[test]
+    fn computer_players() {
+        for first in [Role::Maker, Role::Breaker].iter() {
+            let result = play(
+                Grid::with_size(4, 4, 3),
+                Box::new(ComputerPlayer::new_silent(Player::X)),
+                Box::new(RandoPlayer::new()),
+                *first,
+            );
+            assert_eq!(Some(result.get_winner()), get_winner(result.get_grid()));
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use super::rando::random_rules_move;
+use super::rules::{Agent, KInARow, Rules};
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::time::{Duration, Instant};
+
+// Monte Carlo Tree Search: grows a game tree by playing random games from the current
+// position, steering towards the moves that have done best so far with UCT. It only
+// needs the rules, so it plays any game that implements `Rules`, with any number of
+// players: each node is scored for whoever moved into it.
+pub struct MctsPlayer {
+    player: Player,
+    budget: Budget,
+    exploration: f64,
+    rng: StdRng,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+// One state in the search tree, reached by `chosen`, played by `player`.
+struct Node<R: Rules> {
+    chosen: Option<R::Move>,
+    state: R::State,
+    player: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<R::Move>,
+    // Set once the game is over at this node
+    outcome: Option<R::Outcome>,
+    visits: f64,
+    // Payoffs for `player`, scaled so a win is 1, a draw is half and a loss is 0
+    score: f64,
+}
+
+impl<R: Rules> Node<R> {
+    fn new(
+        rules: &R,
+        state: R::State,
+        chosen: Option<R::Move>,
+        player: Player,
+        parent: Option<usize>,
+    ) -> Self {
+        let outcome = rules.outcome(&state);
+        let untried = if outcome.is_some() {
+            Vec::new()
+        } else {
+            rules.legal_moves(&state)
+        };
+        Self {
+            chosen,
+            state,
+            player,
+            parent,
+            children: Vec::new(),
+            untried,
+            outcome,
+            visits: 0.0,
+            score: 0.0,
+        }
+    }
+}
+
+impl MctsPlayer {
+    const DEFAULT_ITERATIONS: usize = 1000;
+
+    // The player only matters when it takes a seat as a `Playable`, which plays
+    // two-player k-in-a-row with `player` to move.
+    pub fn new(player: Player) -> Self {
+        Self::seeded(player, thread_rng().gen())
+    }
+
+    pub fn seeded(player: Player, seed: u64) -> Self {
+        assert!(
+            player == Player::X || player == Player::O,
+            "Tree search is for X and O only",
+        );
+        Self {
+            player,
+            budget: Budget::Iterations(Self::DEFAULT_ITERATIONS),
+            exploration: 2f64.sqrt(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn with_iterations(mut self, iterations: usize) -> Self {
+        assert!(iterations > 0, "The search needs at least one iteration");
+        self.budget = Budget::Iterations(iterations);
+        self
+    }
+
+    // Searches for as long as the limit allows, rather than a set number of iterations.
+    // Results then depend on the machine, even with a seed.
+    pub fn with_time_limit(mut self, limit: Duration) -> Self {
+        self.budget = Budget::Time(limit);
+        self
+    }
+
+    // How strongly UCT favours moves it has tried less often; the default is √2.
+    pub fn with_exploration(mut self, exploration: f64) -> Self {
+        self.exploration = exploration;
+        self
+    }
+
+    fn uct<R: Rules>(&self, node: &Node<R>, parent_visits: f64) -> f64 {
+        node.score / node.visits + self.exploration * (parent_visits.ln() / node.visits).sqrt()
+    }
+
+    // Adds a child for one of the node's untried moves, and returns it.
+    fn expand<R: Rules>(&mut self, rules: &R, tree: &mut Vec<Node<R>>, parent: usize) -> usize {
+        let untried = &mut tree[parent].untried;
+        let chosen = untried.swap_remove(self.rng.gen_range(0, untried.len()));
+        let player = rules.to_move(&tree[parent].state);
+        let state = rules.apply(&tree[parent].state, &chosen);
+
+        tree.push(Node::new(rules, state, Some(chosen), player, Some(parent)));
+        let child = tree.len() - 1;
+        tree[parent].children.push(child);
+        child
+    }
+
+    // Plays random moves until the game ends, and returns how it ended.
+    fn playout<R: Rules>(&mut self, rules: &R, state: &R::State) -> R::Outcome {
+        let mut state = state.clone();
+        loop {
+            if let Some(outcome) = rules.outcome(&state) {
+                return outcome;
+            }
+            let chosen = random_rules_move(rules, &state, &mut self.rng).expect("No legal moves!");
+            state = rules.apply(&state, &chosen);
+        }
+    }
+
+    fn iterate<R: Rules>(&mut self, rules: &R, tree: &mut Vec<Node<R>>) {
+        let mut node = 0;
+
+        // Selection
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            let parent_visits = tree[node].visits;
+            node = *tree[node]
+                .children
+                .iter()
+                .max_by(|a, b| {
+                    self.uct(&tree[**a], parent_visits)
+                        .partial_cmp(&self.uct(&tree[**b], parent_visits))
+                        .unwrap()
+                })
+                .unwrap();
+        }
+
+        // Expansion and simulation
+        if !tree[node].untried.is_empty() {
+            node = self.expand(rules, tree, node);
+        }
+        let outcome = match &tree[node].outcome {
+            Some(outcome) => outcome.clone(),
+            None => self.playout(rules, &tree[node].state),
+        };
+
+        // Backpropagation
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut tree[index];
+            node.visits += 1.0;
+            node.score += (rules.payoff(&outcome, &node.player) + 1.0) / 2.0;
+            current = node.parent;
+        }
+    }
+}
+
+impl<R: Rules> Agent<R> for MctsPlayer {
+    fn play(&mut self, rules: &R, state: &R::State) -> R::Move {
+        let player = rules.to_move(state);
+        let mut tree = vec![Node::new(rules, state.clone(), None, player, None)];
+
+        match self.budget {
+            Budget::Iterations(iterations) => {
+                for _ in 0..iterations {
+                    self.iterate(rules, &mut tree);
+                }
+            }
+            Budget::Time(limit) => {
+                // Always try at least one move, if there are any to try.
+                let start = Instant::now();
+                while start.elapsed() < limit
+                    || (tree[0].children.is_empty() && !tree[0].untried.is_empty())
+                {
+                    self.iterate(rules, &mut tree);
+                }
+            }
+        }
+
+        // The most visited move is the one the search trusts most.
+        let best = tree[0]
+            .children
+            .iter()
+            .max_by(|a, b| tree[**a].visits.partial_cmp(&tree[**b].visits).unwrap())
+            .expect("No legal moves!");
+        tree[*best].chosen.clone().unwrap()
+    }
+}
+
+impl Playable for MctsPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        let rules = KInARow::new(grid.clone(), vec![self.player, self.player.turn()]);
+        Agent::play(self, &rules, &rules.initial_state())
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+#[cfg(test)]
+mod test_mcts {
+    use super::MctsPlayer;
+    use crate::game::{Coordinate, Grid, Player, Space};
+    use crate::rando::RandoPlayer;
+    use crate::rules::{self, Outcome};
+    use crate::teeko::Teeko;
+    use crate::{play_game, Playable, WinRule};
+    use std::time::Duration;
+
+    #[test]
+    fn takes_win_and_blocks() {
+        // X..
+        // OO.
+        // X..
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+
+        let mut player = MctsPlayer::seeded(Player::X, 1);
+        assert_eq!(Coordinate::new(2, 1), player.play(&grid));
+
+        let mut player = MctsPlayer::seeded(Player::O, 1);
+        assert_eq!(Coordinate::new(2, 1), player.play(&grid));
+    }
+
+    #[test]
+    fn seeded_games_replay() {
+        let game = || {
+            play_game(
+                Grid::with_size(4, 4, 3),
+                vec![
+                    (
+                        Player::X,
+                        Box::new(MctsPlayer::seeded(Player::X, 7).with_iterations(200)),
+                    ),
+                    (
+                        Player::O,
+                        Box::new(MctsPlayer::seeded(Player::O, 8).with_iterations(200)),
+                    ),
+                ],
+                WinRule::FirstToLine,
+            )
+        };
+        assert_eq!(game(), game());
+    }
+
+    #[test]
+    fn time_limit() {
+        let grid = Grid::with_size(9, 9, 5);
+        let mut player =
+            MctsPlayer::seeded(Player::X, 2).with_time_limit(Duration::from_millis(20));
+        assert!(grid.is_legal(&player.play(&grid)));
+    }
+
+    #[test]
+    #[should_panic(expected = "No legal moves!")]
+    fn time_limit_on_full_board() {
+        let grid = Grid::new([
+            [Space::X, Space::O, Space::X],
+            [Space::X, Space::O, Space::O],
+            [Space::O, Space::X, Space::X],
+        ]);
+        let mut player = MctsPlayer::seeded(Player::X, 2).with_time_limit(Duration::from_millis(1));
+        player.play(&grid);
+    }
+
+    #[test]
+    fn plays_teeko() {
+        let record = rules::play(
+            &Teeko::new(40),
+            vec![
+                (
+                    Player::X,
+                    Box::new(MctsPlayer::seeded(Player::X, 3).with_iterations(100)),
+                ),
+                (Player::O, Box::new(RandoPlayer::seeded(4))),
+            ],
+        );
+        assert_eq!(&Outcome::Win(Player::X), record.get_outcome());
+    }
+
+    #[test]
+    fn beats_random_player() {
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..10 {
+            let standings = play_game(
+                Grid::empty(),
+                vec![
+                    (Player::X, Box::new(RandoPlayer::seeded(seed))),
+                    (
+                        Player::O,
+                        Box::new(MctsPlayer::seeded(Player::O, seed).with_iterations(500)),
+                    ),
+                ],
+                WinRule::FirstToLine,
+            );
+            match standings.get_winner() {
+                Some(Player::O) => wins += 1,
+                Some(_) => losses += 1,
+                None => {}
+            }
+        }
+        assert!(wins > losses);
+    }
+}
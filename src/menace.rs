@@ -0,0 +1,426 @@
+use super::file::FileError;
+use super::symmetry::{canonical, Symmetry};
+use super::{play_game, Coordinate, Grid, Playable, Player, Standings, WinRule};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// Donald Michie's MENACE (Matchbox Educable Noughts And Crosses Engine), which learned
+// tic-tac-toe from a pile of matchboxes, one for every position it could face. Each box
+// holds beads for the moves open in that position, and MENACE moves by drawing a bead
+// at random. After each game, every box it drew from gets more beads for the move it
+// made if it won or drew, and loses one if it lost, so good moves come up more often.
+//
+// Positions that are turns or flips of each other share a box, and a box only has beads
+// for moves that aren't turns of each other, so the empty board's box only holds a
+// corner, an edge and the centre.
+#[derive(Debug, Clone)]
+pub struct Menace {
+    player: Player,
+    // Beads for each space of the canonical board, by canonical key
+    boxes: HashMap<String, Vec<u32>>,
+    rng: StdRng,
+}
+
+impl Menace {
+    const SIZE: usize = 3;
+    const WIN_BEADS: u32 = 3;
+    const DRAW_BEADS: u32 = 1;
+    const LOSS_BEADS: u32 = 1;
+
+    pub fn new(player: Player) -> Self {
+        Self {
+            player,
+            boxes: HashMap::new(),
+            rng: StdRng::seed_from_u64(thread_rng().gen()),
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, format!("{}", self))
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    // How many boxes have been opened so far.
+    pub fn matchboxes(&self) -> usize {
+        self.boxes.len()
+    }
+
+    // The beads in the box for this position, by the move they stand for on this board.
+    // Moves without beads are left out.
+    pub fn beads(&self, grid: &Grid) -> Vec<(Coordinate, u32)> {
+        let (key, symmetry) = canonical(grid);
+        let beads = match self.boxes.get(&key) {
+            Some(beads) => beads.clone(),
+            None => new_box(&key),
+        };
+
+        let mut moves: Vec<(Coordinate, u32)> = beads
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(index, count)| (symmetry.invert(&coordinate(index), Self::SIZE), count))
+            .collect();
+        moves.sort_by_key(|(c, _)| (c.y(), c.x()));
+        moves
+    }
+
+    // Adds and removes beads for MENACE's moves in a finished game.
+    pub fn learn(&mut self, standings: &Standings) {
+        let player = self.player;
+        let change = |beads: &mut u32| match standings.get_winner() {
+            Some(winner) if winner == player => *beads += Self::WIN_BEADS,
+            Some(_) => *beads = beads.saturating_sub(Self::LOSS_BEADS),
+            None => *beads += Self::DRAW_BEADS,
+        };
+
+        let mut grid = Grid::empty();
+        for turn in standings.get_turns() {
+            for placement in turn.get_placements() {
+                if turn.get_player() == player {
+                    let (key, symmetry) = canonical(&grid);
+                    let index = index(&symmetry.apply(placement, Self::SIZE));
+                    let beads = self
+                        .boxes
+                        .entry(key.clone())
+                        .or_insert_with(|| new_box(&key));
+                    change(&mut beads[index]);
+                }
+                grid.set_space(placement, &turn.get_player()).ok();
+            }
+        }
+    }
+
+    // Plays `games` games against opponents made by `opponent` for the other side,
+    // learning from each one, and returns the winners in order.
+    pub fn train<F>(&mut self, games: usize, mut opponent: F) -> Vec<Option<Player>>
+    where
+        F: FnMut(Player) -> Box<dyn Playable>,
+    {
+        (0..games)
+            .map(|_| {
+                let menace = Self {
+                    rng: StdRng::seed_from_u64(self.rng.gen()),
+                    ..self.clone()
+                };
+                let other = self.player.turn();
+                let mut seats: Vec<(Player, Box<dyn Playable>)> =
+                    vec![(self.player, Box::new(menace)), (other, opponent(other))];
+                seats.sort_by_key(|(player, _)| *player != Player::X);
+
+                let standings = play_game(Grid::empty(), seats, WinRule::FirstToLine);
+                self.learn(&standings);
+                standings.get_winner()
+            })
+            .collect()
+    }
+}
+
+impl Playable for Menace {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        assert!(
+            grid.width() == Self::SIZE && grid.height() == Self::SIZE && !grid.has_captures(),
+            "MENACE only plays on a plain 3x3 board",
+        );
+
+        let (key, symmetry) = canonical(grid);
+        let beads = self
+            .boxes
+            .entry(key.clone())
+            .or_insert_with(|| new_box(&key));
+
+        // Michie's MENACE resigned when a box ran out of beads; this one starts the box
+        // over instead.
+        if beads.iter().all(|count| *count == 0) {
+            *beads = new_box(&key);
+        }
+
+        let index = WeightedIndex::new(beads.iter())
+            .expect("No legal moves!")
+            .sample(&mut self.rng);
+        symmetry.invert(&coordinate(index), Self::SIZE)
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+// A full box for the canonical board with this key: fewer beads the later in the game it
+// comes up, and only one move from each set that are turns of each other.
+fn new_box(key: &str) -> Vec<u32> {
+    let spaces: Vec<char> = key.chars().collect();
+    let stones = spaces.iter().filter(|space| **space != '.').count() as u32;
+    let beads = 4u32.saturating_sub(stones / 2).max(1);
+
+    let turns: Vec<Symmetry> = Symmetry::all()
+        .filter(|symmetry| {
+            (0..spaces.len())
+                .all(|i| spaces[index(&symmetry.apply(&coordinate(i), Menace::SIZE))] == spaces[i])
+        })
+        .collect();
+
+    (0..spaces.len())
+        .map(|i| {
+            let first = turns
+                .iter()
+                .all(|symmetry| index(&symmetry.apply(&coordinate(i), Menace::SIZE)) >= i);
+            if spaces[i] == '.' && first {
+                beads
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+// The board a key was made from.
+fn grid(key: &str) -> Grid {
+    let mut grid = Grid::empty();
+    for (i, space) in key.chars().enumerate() {
+        let player = match space {
+            'X' => Player::X,
+            'O' => Player::O,
+            _ => continue,
+        };
+        grid.set_space(&coordinate(i), &player).ok();
+    }
+    grid
+}
+
+fn coordinate(index: usize) -> Coordinate {
+    Coordinate::new(index % Menace::SIZE, index / Menace::SIZE)
+}
+
+fn index(coordinate: &Coordinate) -> usize {
+    coordinate.y() * Menace::SIZE + coordinate.x()
+}
+
+// One box per line, as its canonical key followed by the beads for each space, eg.
+//
+//     # MENACE matchboxes
+//     player X
+//     ......... 4 4 0 0 4 0 0 0 0
+impl fmt::Display for Menace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# MENACE matchboxes")?;
+        writeln!(f, "player {}", self.player)?;
+
+        let mut keys: Vec<&String> = self.boxes.keys().collect();
+        keys.sort();
+        for key in keys {
+            write!(f, "{}", key)?;
+            for count in self.boxes[key].iter() {
+                write!(f, " {}", count)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Menace {
+    type Err = FileError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let spaces = Menace::SIZE * Menace::SIZE;
+        let mut player = None;
+        let mut boxes = HashMap::new();
+
+        for (number, line) in raw.lines().enumerate() {
+            let error =
+                |message: &str| FileError::Parse("matchboxes", number + 1, message.to_string());
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            } else if let Some(symbol) = line.strip_prefix("player ") {
+                player = match symbol.trim() {
+                    "X" => Some(Player::X),
+                    "O" => Some(Player::O),
+                    _ => return Err(error("expected player X or O")),
+                };
+            } else {
+                let mut parts = line.split_whitespace();
+                let key = parts.next().unwrap();
+                if key.len() != spaces || key.chars().any(|c| !".XO".contains(c)) {
+                    return Err(error("expected a board like X...O...."));
+                }
+
+                let beads = parts
+                    .map(|count| count.parse::<u32>())
+                    .collect::<Result<Vec<u32>, _>>()
+                    .map_err(|_| error("expected a count of beads"))?;
+                if beads.len() != spaces {
+                    return Err(error("expected a count of beads for every space"));
+                } else if key
+                    .chars()
+                    .zip(beads.iter())
+                    .any(|(c, b)| c != '.' && *b > 0)
+                {
+                    return Err(error("beads for a space that's already taken"));
+                } else if canonical(&grid(key)).0 != key {
+                    return Err(error("expected the board's canonical key"));
+                }
+                boxes.insert(key.to_string(), beads);
+            }
+        }
+
+        let player =
+            player.ok_or_else(|| FileError::Parse("matchboxes", 0, "no player".to_string()))?;
+        Ok(Self {
+            boxes,
+            ..Menace::new(player)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_menace {
+    use super::{FileError, Menace};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::Playable;
+    use std::env;
+
+    #[test]
+    fn opening_box() {
+        let menace = Menace::new(Player::X);
+        assert_eq!(
+            vec![
+                (Coordinate::new(0, 0), 4),
+                (Coordinate::new(1, 0), 4),
+                (Coordinate::new(1, 1), 4),
+            ],
+            menace.beads(&Grid::empty()),
+        );
+
+        // After a corner opening, O's box has one of each kind of reply.
+        let mut grid = Grid::empty();
+        grid.set_space(&"C3".parse().unwrap(), &Player::X).unwrap();
+        assert_eq!(5, Menace::new(Player::O).beads(&grid).len());
+    }
+
+    #[test]
+    fn plays_legal_moves() {
+        let mut grid = Grid::empty();
+        grid.set_space(&"B2".parse().unwrap(), &Player::X).unwrap();
+        grid.set_space(&"A1".parse().unwrap(), &Player::O).unwrap();
+
+        let mut menace = Menace::new(Player::X).with_seed(3);
+        for _ in 0..20 {
+            assert!(grid.is_legal(&menace.play(&grid)));
+        }
+    }
+
+    #[test]
+    fn learns_from_results() {
+        let mut menace = Menace::new(Player::X).with_seed(1);
+        let winners = menace.train(1, |player| Box::new(ComputerPlayer::new_silent(player)));
+
+        let beads: u32 = menace.beads(&Grid::empty()).iter().map(|(_, b)| b).sum();
+        match winners[0] {
+            Some(Player::X) => assert_eq!(15, beads),
+            Some(_) => assert_eq!(11, beads),
+            None => assert_eq!(13, beads),
+        }
+        assert!(menace.matchboxes() >= 3);
+    }
+
+    #[test]
+    fn improves_against_random_player() {
+        let mut menace = Menace::new(Player::X).with_seed(2);
+        let mut seed = 0;
+        let winners = menace.train(3000, |_| {
+            seed += 1;
+            Box::new(RandoPlayer::seeded(seed))
+        });
+
+        let losses = |games: &[Option<Player>]| {
+            games
+                .iter()
+                .filter(|winner| **winner == Some(Player::O))
+                .count()
+        };
+        assert!(losses(&winners[2500..]) < losses(&winners[..500]));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut menace = Menace::new(Player::O).with_seed(4);
+        menace.train(50, |_| Box::new(RandoPlayer::seeded(5)));
+
+        let path = env::temp_dir().join(format!("menace-{}.txt", std::process::id()));
+        menace.save(&path).unwrap();
+        let loaded = Menace::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(Player::O, loaded.get_player());
+        assert_eq!(menace.matchboxes(), loaded.matchboxes());
+        assert_eq!(format!("{}", menace), format!("{}", loaded));
+
+        match Menace::load(env::temp_dir().join("no-such-matchboxes.txt")) {
+            Err(FileError::Io(_)) => {}
+            other => panic!("Expected an IO error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn from_str_invalid() {
+        let message = |raw: &str| format!("{}", raw.parse::<Menace>().unwrap_err());
+
+        assert_eq!(
+            "Invalid matchboxes: no player",
+            message("......... 4 4 0 0 4 0 0 0 0"),
+        );
+        assert_eq!(
+            "Invalid matchboxes on line 1: expected player X or O",
+            message("player Δ"),
+        );
+        assert_eq!(
+            "Invalid matchboxes on line 2: expected a count of beads for every space",
+            message("player X\n......... 4 4"),
+        );
+        assert_eq!(
+            "Invalid matchboxes on line 2: expected a board like X...O....",
+            message("player X\nX..O 1 2"),
+        );
+        assert_eq!(
+            "Invalid matchboxes on line 2: beads for a space that's already taken",
+            message("player O\n........X 4 4 0 4 4 0 0 0 1"),
+        );
+
+        // The same board turned half way round, which MENACE would never look up.
+        assert_eq!(
+            "Invalid matchboxes on line 2: expected the board's canonical key",
+            message("player O\nX........ 0 4 4 0 4 0 0 0 0"),
+        );
+        assert!("player O\n........X 4 4 0 4 4 0 0 0 0"
+            .parse::<Menace>()
+            .is_ok());
+    }
+}
@@ -0,0 +1,608 @@
+use super::file::FileError;
+use super::solver::Solver;
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+const SPACES: usize = 9;
+const OUTPUTS: usize = SPACES + 1;
+
+// A small multilayer perceptron for classic tic-tac-toe, in plain Rust on the CPU. It
+// reads the nine spaces from the side of the player to move (1 for their stones, -1 for
+// the opponent's, 0 for empty), passes them through one layer of tanh units, and puts
+// out a score for each space and a value. The scores become move probabilities with a
+// softmax over the legal moves; the value is tanh'd into -1 (lost) to 1 (won).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Network {
+    hidden: usize,
+    // Input weights for each hidden unit in turn, and their biases
+    hidden_weights: Vec<f64>,
+    hidden_biases: Vec<f64>,
+    // Hidden weights for each output in turn: the nine spaces, then the value
+    output_weights: Vec<f64>,
+    output_biases: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Output {
+    policy: Vec<f64>,
+    value: f64,
+}
+
+// One position to learn from: the move probabilities and value the network should give.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Example {
+    input: Vec<f64>,
+    legal: Vec<bool>,
+    policy: Vec<f64>,
+    value: f64,
+}
+
+// Plays the network's most likely move.
+#[derive(Debug, Clone)]
+pub struct NeuralPlayer {
+    network: Network,
+    player: Player,
+}
+
+// Intermediate values from a forward pass, kept for backpropagation.
+struct Pass {
+    input: Vec<f64>,
+    hidden: Vec<f64>,
+    policy: Vec<f64>,
+    value: f64,
+}
+
+impl Network {
+    pub const DEFAULT_HIDDEN: usize = 64;
+
+    // Starts from small random weights, so the same seed always gives the same network.
+    pub fn new(hidden: usize, seed: u64) -> Self {
+        assert!(hidden > 0, "The network needs at least one hidden unit");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut weights = |count: usize, inputs: usize| -> Vec<f64> {
+            let scale = 1.0 / (inputs as f64).sqrt();
+            (0..count).map(|_| rng.gen_range(-scale, scale)).collect()
+        };
+
+        Self {
+            hidden,
+            hidden_weights: weights(hidden * SPACES, SPACES),
+            hidden_biases: vec![0.0; hidden],
+            output_weights: weights(OUTPUTS * hidden, hidden),
+            output_biases: vec![0.0; OUTPUTS],
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, format!("{}", self))
+    }
+
+    pub fn evaluate(&self, grid: &Grid, player: &Player) -> Output {
+        let pass = self.forward(encode(grid, player), &legal(grid));
+        Output {
+            policy: pass.policy,
+            value: pass.value,
+        }
+    }
+
+    // Trains by stochastic gradient descent on cross-entropy for the moves plus squared
+    // error for the value, visiting the examples in a seeded random order each epoch.
+    // Returns the average loss over the last epoch.
+    pub fn train(&mut self, examples: &[Example], epochs: usize, rate: f64, seed: u64) -> f64 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut order: Vec<usize> = (0..examples.len()).collect();
+        let mut loss = 0.0;
+
+        for _ in 0..epochs {
+            order.shuffle(&mut rng);
+            loss = 0.0;
+
+            for example in order.iter().map(|i| &examples[*i]) {
+                let pass = self.forward(example.input.clone(), &example.legal);
+                loss += (0..SPACES)
+                    .filter(|i| example.policy[*i] > 0.0)
+                    .map(|i| -example.policy[i] * pass.policy[i].max(1e-12).ln())
+                    .sum::<f64>()
+                    + (pass.value - example.value).powi(2);
+
+                let policy_gradient: Vec<f64> = (0..SPACES)
+                    .map(|i| pass.policy[i] - example.policy[i])
+                    .collect();
+                let value_gradient = 2.0 * (pass.value - example.value);
+                self.backward(&pass, &policy_gradient, value_gradient, rate);
+            }
+            loss /= examples.len().max(1) as f64;
+        }
+
+        loss
+    }
+
+    // The share of examples where the most likely move is one of the example's moves.
+    pub fn accuracy(&self, examples: &[Example]) -> f64 {
+        let correct = examples
+            .iter()
+            .filter(|example| {
+                let pass = self.forward(example.input.clone(), &example.legal);
+                let output = Output {
+                    policy: pass.policy,
+                    value: pass.value,
+                };
+                example.policy[index(&output.best_move())] > 0.0
+            })
+            .count();
+        correct as f64 / examples.len().max(1) as f64
+    }
+
+    // Learns from games against itself, moving at random in proportion to its own policy.
+    // Each move is made more likely if it did better than the network expected and less
+    // likely if it did worse (policy gradient with the value as a baseline), and the value
+    // is trained towards each game's result.
+    pub fn train_self_play(&mut self, games: usize, rate: f64, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for _ in 0..games {
+            let mut grid = Grid::empty();
+            let mut player = Player::X;
+            let mut moves = Vec::new();
+
+            let winner = loop {
+                let pass = self.forward(encode(&grid, &player), &legal(&grid));
+                let space = (0..SPACES)
+                    .collect::<Vec<usize>>()
+                    .choose_weighted(&mut rng, |i| pass.policy[*i])
+                    .copied()
+                    .expect("No legal moves!");
+                let coordinate = coordinate(space);
+                let wins = grid.wins_with(&coordinate, &player);
+                moves.push((player, pass, space));
+
+                grid.set_space(&coordinate, &player).ok();
+                if wins {
+                    break Some(player);
+                } else if !grid.has_legal_moves() {
+                    break None;
+                }
+                player = player.turn();
+            };
+
+            for (player, pass, space) in moves.iter() {
+                let result = match winner {
+                    Some(winner) if winner == *player => 1.0,
+                    Some(_) => -1.0,
+                    None => 0.0,
+                };
+                let advantage = result - pass.value;
+                let policy_gradient: Vec<f64> = (0..SPACES)
+                    .map(|i| {
+                        let chosen = if i == *space { 1.0 } else { 0.0 };
+                        advantage * (pass.policy[i] - chosen)
+                    })
+                    .collect();
+                self.backward(pass, &policy_gradient, 2.0 * (pass.value - result), rate);
+            }
+        }
+    }
+
+    fn forward(&self, input: Vec<f64>, legal: &[bool]) -> Pass {
+        let hidden: Vec<f64> = (0..self.hidden)
+            .map(|j| {
+                let weights = &self.hidden_weights[j * SPACES..(j + 1) * SPACES];
+                let sum: f64 = weights.iter().zip(input.iter()).map(|(w, x)| w * x).sum();
+                (sum + self.hidden_biases[j]).tanh()
+            })
+            .collect();
+
+        let outputs: Vec<f64> = (0..OUTPUTS)
+            .map(|k| {
+                let weights = &self.output_weights[k * self.hidden..(k + 1) * self.hidden];
+                let sum: f64 = weights.iter().zip(hidden.iter()).map(|(w, h)| w * h).sum();
+                sum + self.output_biases[k]
+            })
+            .collect();
+
+        // Softmax over the legal moves only; illegal ones get no probability at all.
+        let max = (0..SPACES)
+            .filter(|i| legal[*i])
+            .map(|i| outputs[i])
+            .fold(f64::NEG_INFINITY, f64::max);
+        let exponents: Vec<f64> = (0..SPACES)
+            .map(|i| {
+                if legal[i] {
+                    (outputs[i] - max).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+        let total: f64 = exponents.iter().sum();
+
+        // A finished board has no moves to share the probability between, only a value.
+        let policy = if total > 0.0 {
+            exponents.iter().map(|e| e / total).collect()
+        } else {
+            vec![0.0; SPACES]
+        };
+
+        Pass {
+            input,
+            hidden,
+            policy,
+            value: outputs[SPACES].tanh(),
+        }
+    }
+
+    // One step of gradient descent, given the loss gradients for the move scores (before
+    // the softmax) and for the value (after the tanh).
+    fn backward(&mut self, pass: &Pass, policy_gradient: &[f64], value_gradient: f64, rate: f64) {
+        let mut output_gradients = policy_gradient.to_vec();
+        output_gradients.push(value_gradient * (1.0 - pass.value * pass.value));
+
+        let hidden_gradients: Vec<f64> = (0..self.hidden)
+            .map(|j| {
+                let sum: f64 = (0..OUTPUTS)
+                    .map(|k| self.output_weights[k * self.hidden + j] * output_gradients[k])
+                    .sum();
+                sum * (1.0 - pass.hidden[j] * pass.hidden[j])
+            })
+            .collect();
+
+        for (k, gradient) in output_gradients.iter().enumerate() {
+            for (j, h) in pass.hidden.iter().enumerate() {
+                self.output_weights[k * self.hidden + j] -= rate * gradient * h;
+            }
+            self.output_biases[k] -= rate * gradient;
+        }
+
+        for (j, gradient) in hidden_gradients.iter().enumerate() {
+            for (i, x) in pass.input.iter().enumerate() {
+                self.hidden_weights[j * SPACES + i] -= rate * gradient * x;
+            }
+            self.hidden_biases[j] -= rate * gradient;
+        }
+    }
+}
+
+impl Output {
+    // The probability of each space, row by row
+    pub fn get_policy(&self) -> &[f64] {
+        &self.policy
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    // The most likely move, and the first of them in row order on a tie.
+    pub fn best_move(&self) -> Coordinate {
+        let best = (0..SPACES).fold(0, |best, i| {
+            if self.policy[i] > self.policy[best] {
+                i
+            } else {
+                best
+            }
+        });
+        coordinate(best)
+    }
+}
+
+impl Example {
+    pub fn new(grid: &Grid, player: &Player, moves: &[Coordinate], value: f64) -> Self {
+        let mut policy = vec![0.0; SPACES];
+        for c in moves.iter() {
+            policy[index(c)] = 1.0 / moves.len() as f64;
+        }
+        Self {
+            input: encode(grid, player),
+            legal: legal(grid),
+            policy,
+            value,
+        }
+    }
+}
+
+// Every position that can come up in a game, labelled by the solver: the best moves share
+// the probability evenly, and the value is the result with best play.
+pub fn solver_examples(solver: &mut Solver) -> Vec<Example> {
+    let mut examples = Vec::new();
+    let mut seen = HashSet::new();
+    let mut positions = vec![(Grid::empty(), Player::X)];
+
+    while let Some((grid, player)) = positions.pop() {
+        if !seen.insert(grid.clone()) {
+            continue;
+        }
+
+        let value = solver.value(&grid, &player) as f64;
+        examples.push(Example::new(
+            &grid,
+            &player,
+            &solver.best_moves(&grid, &player),
+            value,
+        ));
+
+        for c in grid.legal_moves() {
+            if !grid.wins_with(&c, &player) {
+                let mut next = grid.clone();
+                next.set_space(&c, &player).ok();
+                if next.has_legal_moves() {
+                    positions.push((next, player.turn()));
+                }
+            }
+        }
+    }
+
+    examples
+}
+
+impl NeuralPlayer {
+    pub fn new(network: Network, player: Player) -> Self {
+        Self { network, player }
+    }
+}
+
+impl Playable for NeuralPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        self.network.evaluate(grid, &self.player).best_move()
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+}
+
+fn encode(grid: &Grid, player: &Player) -> Vec<f64> {
+    assert!(
+        grid.width() == 3 && grid.height() == 3,
+        "The network only reads 3x3 boards"
+    );
+    (0..SPACES)
+        .map(|i| match grid.get_space(&coordinate(i)).get_player() {
+            Some(p) if p == *player => 1.0,
+            Some(_) => -1.0,
+            None => 0.0,
+        })
+        .collect()
+}
+
+fn legal(grid: &Grid) -> Vec<bool> {
+    (0..SPACES).map(|i| grid.is_legal(&coordinate(i))).collect()
+}
+
+fn coordinate(index: usize) -> Coordinate {
+    Coordinate::new(index % 3, index / 3)
+}
+
+fn index(coordinate: &Coordinate) -> usize {
+    coordinate.y() * 3 + coordinate.x()
+}
+
+// A line with the number of hidden units, then one line per hidden unit (its nine input
+// weights and its bias) and one per output (its hidden weights and its bias), eg.
+//
+//     # Multilayer perceptron
+//     hidden 32
+//     h 0.12 -0.3 ... 0
+//     o -0.05 0.2 ... 0.01
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Multilayer perceptron")?;
+        writeln!(f, "hidden {}", self.hidden)?;
+
+        let unit = |f: &mut fmt::Formatter<'_>, tag: &str, weights: &[f64], bias: f64| {
+            write!(f, "{}", tag)?;
+            for weight in weights.iter().chain(Some(&bias)) {
+                write!(f, " {}", weight)?;
+            }
+            writeln!(f)
+        };
+        for j in 0..self.hidden {
+            let weights = &self.hidden_weights[j * SPACES..(j + 1) * SPACES];
+            unit(f, "h", weights, self.hidden_biases[j])?;
+        }
+        for k in 0..OUTPUTS {
+            let weights = &self.output_weights[k * self.hidden..(k + 1) * self.hidden];
+            unit(f, "o", weights, self.output_biases[k])?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Network {
+    type Err = FileError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut hidden = None;
+        let mut network = Network {
+            hidden: 0,
+            hidden_weights: Vec::new(),
+            hidden_biases: Vec::new(),
+            output_weights: Vec::new(),
+            output_biases: Vec::new(),
+        };
+
+        for (number, line) in raw.lines().enumerate() {
+            let error =
+                |message: &str| FileError::Parse("network", number + 1, message.to_string());
+            let line = line.trim();
+            let mut parts = line.split_whitespace();
+
+            match parts.next() {
+                None => continue,
+                Some(tag) if tag.starts_with('#') => continue,
+                Some("hidden") => match parts.next().and_then(|n| n.parse::<usize>().ok()) {
+                    Some(n) if n > 0 => hidden = Some(n),
+                    _ => return Err(error("expected a number of hidden units")),
+                },
+                Some(tag) if tag == "h" || tag == "o" => {
+                    let units = hidden.ok_or_else(|| error("expected the hidden line first"))?;
+                    let values = parts
+                        .map(|value| value.parse::<f64>())
+                        .collect::<Result<Vec<f64>, _>>()
+                        .map_err(|_| error("expected a number"))?;
+                    let (inputs, weights, biases) = if tag == "h" {
+                        (
+                            SPACES,
+                            &mut network.hidden_weights,
+                            &mut network.hidden_biases,
+                        )
+                    } else {
+                        (
+                            units,
+                            &mut network.output_weights,
+                            &mut network.output_biases,
+                        )
+                    };
+                    if values.len() != inputs + 1 {
+                        return Err(error(&format!("expected {} weights and a bias", inputs)));
+                    }
+                    weights.extend_from_slice(&values[..inputs]);
+                    biases.push(values[inputs]);
+                }
+                Some(_) => return Err(error("expected hidden, h or o")),
+            }
+        }
+
+        network.hidden =
+            hidden.ok_or_else(|| FileError::Parse("network", 0, "no hidden line".to_string()))?;
+        if network.hidden_biases.len() != network.hidden || network.output_biases.len() != OUTPUTS {
+            return Err(FileError::Parse(
+                "network",
+                0,
+                format!(
+                    "expected {} hidden units and {} outputs",
+                    network.hidden, OUTPUTS
+                ),
+            ));
+        }
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod test_neural {
+    use super::{solver_examples, Network, NeuralPlayer};
+    use crate::game::{Coordinate, Grid, Player, Space};
+    use crate::rando::RandoPlayer;
+    use crate::solver::Solver;
+    use crate::{play_seeded, Playable};
+    use std::env;
+
+    #[test]
+    fn outputs() {
+        let network = Network::new(8, 1);
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(1, 1), &Player::X).unwrap();
+
+        let output = network.evaluate(&grid, &Player::O);
+        let total: f64 = output.get_policy().iter().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert_eq!(0.0, output.get_policy()[4]);
+        assert!(output.get_value().abs() < 1.0);
+        assert_eq!(network, Network::new(8, 1));
+
+        // A full board still has a value, but no moves.
+        let full = Grid::new([
+            [Space::X, Space::O, Space::X],
+            [Space::X, Space::O, Space::O],
+            [Space::O, Space::X, Space::X],
+        ]);
+        let output = network.evaluate(&full, &Player::X);
+        assert!(output.get_policy().iter().all(|p| *p == 0.0));
+        assert!(output.get_value().abs() < 1.0);
+    }
+
+    #[test]
+    fn learns_from_solver() {
+        let examples = solver_examples(&mut Solver::new());
+        assert_eq!(4520, examples.len());
+
+        let mut network = Network::new(Network::DEFAULT_HIDDEN, 2);
+        let before = network.accuracy(&examples);
+        let first = network.train(&examples, 1, 0.01, 3);
+        let last = network.train(&examples, 4, 0.01, 3);
+        assert!(last < first);
+        assert!(network.accuracy(&examples) > before);
+
+        // XX.
+        // OO.
+        // ...
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+        let output = network.evaluate(&grid, &Player::X);
+        assert_eq!(Coordinate::new(2, 0), output.best_move());
+        assert!(output.get_value() > 0.5);
+
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..20 {
+            let grid = play_seeded(
+                Box::new(NeuralPlayer::new(network.clone(), Player::X)),
+                Box::new(RandoPlayer::new()),
+                seed,
+            );
+            match grid.get_winner() {
+                Some(Player::X) => wins += 1,
+                Some(_) => losses += 1,
+                None => {}
+            }
+        }
+        assert!(wins > losses);
+    }
+
+    #[test]
+    fn self_play_is_seeded() {
+        let trained = |seed| {
+            let mut network = Network::new(8, 4);
+            network.train_self_play(20, 0.01, seed);
+            network
+        };
+        assert_eq!(trained(5), trained(5));
+        assert_ne!(Network::new(8, 4), trained(5));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let network = Network::new(4, 6);
+        let path = env::temp_dir().join(format!("network-{}.txt", std::process::id()));
+        network.save(&path).unwrap();
+        let loaded = Network::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(network, loaded.unwrap());
+
+        let mut player = NeuralPlayer::new(network, Player::X);
+        assert!(Grid::empty().is_legal(&player.play(&Grid::empty())));
+    }
+
+    #[test]
+    fn from_str_invalid() {
+        let message = |raw: &str| format!("{}", raw.parse::<Network>().unwrap_err());
+
+        assert_eq!("Invalid network: no hidden line", message("# nothing"));
+        assert_eq!(
+            "Invalid network on line 1: expected the hidden line first",
+            message("h 1 2 3"),
+        );
+        assert_eq!(
+            "Invalid network on line 2: expected 9 weights and a bias",
+            message("hidden 1\nh 1 2 3"),
+        );
+        assert_eq!(
+            "Invalid network: expected 1 hidden units and 10 outputs",
+            message("hidden 1\nh 0 0 0 0 0 0 0 0 0 0"),
+        );
+    }
+}
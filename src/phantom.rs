@@ -0,0 +1,433 @@
+use super::rando::RandoPlayer;
+use super::rules::{self, Agent, Outcome, Rules};
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+// Phantom tic-tac-toe: each player only sees their own marks, plus any of their
+// opponent's they've bumped into.
+pub trait PhantomPlayable {
+    fn play(&mut self, observation: &Observation) -> Coordinate;
+
+    // Restarts any random choices the player makes from `seed`, as `Playable::reseed()`.
+    fn reseed(&mut self, _seed: u64) {}
+}
+
+// What one player knows about the board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observation {
+    player: Player,
+    grid: Grid,
+    hidden: usize,
+}
+
+impl Observation {
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    // The player's own marks, and whichever opposing marks they've discovered.
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // How many opposing marks are somewhere among the spaces that look empty.
+    pub fn get_hidden(&self) -> usize {
+        self.hidden
+    }
+}
+
+// Phantom tic-tac-toe under the generic rules. A player who tries to move onto a hidden
+// mark is told the space is taken, and moves again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Phantom {
+    grid: Grid,
+}
+
+impl Phantom {
+    pub fn new(grid: Grid) -> Self {
+        Self { grid }
+    }
+}
+
+// The real board, what X and what O know of it, and whose turn it is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhantomState {
+    grid: Grid,
+    views: [Grid; 2],
+    player: Player,
+}
+
+impl PhantomState {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // What the player to move knows about the board.
+    pub fn observation(&self) -> Observation {
+        let view = &self.views[Self::seat(&self.player)];
+        let opponent = self.player.turn();
+        Observation {
+            player: self.player,
+            grid: view.clone(),
+            hidden: self.grid.count(&opponent) - view.count(&opponent),
+        }
+    }
+
+    fn seat(player: &Player) -> usize {
+        if *player == Player::X {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Rules for Phantom {
+    type State = PhantomState;
+    type Move = Coordinate;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> PhantomState {
+        PhantomState {
+            grid: self.grid.clone(),
+            views: [self.grid.clone(), self.grid.clone()],
+            player: Player::X,
+        }
+    }
+
+    fn to_move(&self, state: &PhantomState) -> Player {
+        state.player
+    }
+
+    // Any space that looks empty to the player to move.
+    fn legal_moves(&self, state: &PhantomState) -> Vec<Coordinate> {
+        state.views[PhantomState::seat(&state.player)].legal_moves()
+    }
+
+    fn apply(&self, state: &PhantomState, chosen: &Coordinate) -> PhantomState {
+        let mut next = state.clone();
+        let player = state.player;
+        let view = &mut next.views[PhantomState::seat(&player)];
+
+        if state.grid.is_legal(chosen) {
+            next.grid.set_space(chosen, &player).ok();
+            view.set_space(chosen, &player).ok();
+            next.player = player.turn();
+        } else {
+            // Reveal the hidden mark to the player who ran into it.
+            view.set_space(chosen, &player.turn()).ok();
+        }
+        next
+    }
+
+    fn outcome(&self, state: &PhantomState) -> Option<Outcome> {
+        match state.grid.get_winner() {
+            Some(winner) => Some(Outcome::Win(winner)),
+            None if !state.grid.has_legal_moves() => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, player: &Player) -> f64 {
+        outcome.payoff(player)
+    }
+}
+
+// Lets any `PhantomPlayable` take a seat under the generic rules.
+impl Agent<Phantom> for Box<dyn PhantomPlayable> {
+    fn play(&mut self, _rules: &Phantom, state: &PhantomState) -> Coordinate {
+        PhantomPlayable::play(self.as_mut(), &state.observation())
+    }
+}
+
+// Referees a game of Phantom tic-tac-toe, X first.
+pub fn play(
+    grid: Grid,
+    player_x: Box<dyn PhantomPlayable>,
+    player_o: Box<dyn PhantomPlayable>,
+) -> PhantomResult {
+    let rules = Phantom::new(grid);
+    let record = rules::play(
+        &rules,
+        vec![
+            (Player::X, Box::new(player_x)),
+            (Player::O, Box::new(player_o)),
+        ],
+    );
+
+    // Replay the game to find which moves were turned away.
+    let mut grid = rules.grid;
+    let mut attempts = Vec::new();
+    for (player, coordinate) in record.get_moves() {
+        if grid.is_legal(coordinate) {
+            grid.set_space(coordinate, player).ok();
+        } else {
+            attempts.push((*player, *coordinate));
+        }
+    }
+
+    PhantomResult { grid, attempts }
+}
+
+// Referees a game with both players reseeded from one seed, so it replays exactly.
+pub fn play_seeded(
+    grid: Grid,
+    mut player_x: Box<dyn PhantomPlayable>,
+    mut player_o: Box<dyn PhantomPlayable>,
+    seed: u64,
+) -> PhantomResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    player_x.reseed(rng.gen());
+    player_o.reseed(rng.gen());
+    play(grid, player_x, player_o)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhantomResult {
+    grid: Grid,
+    attempts: Vec<(Player, Coordinate)>,
+}
+
+impl PhantomResult {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // Every move that was turned away because the space was secretly taken, in order.
+    pub fn get_attempts(&self) -> &[(Player, Coordinate)] {
+        &self.attempts
+    }
+}
+
+impl PhantomPlayable for RandoPlayer {
+    fn play(&mut self, observation: &Observation) -> Coordinate {
+        Playable::play(self, observation.get_grid())
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        Playable::reseed(self, seed);
+    }
+}
+
+// A baseline player for hidden information. It samples boards consistent with what it
+// has seen, scores each candidate move on every sample, and plays the best on average.
+pub struct BeliefPlayer {
+    samples: usize,
+    rng: StdRng,
+}
+
+impl BeliefPlayer {
+    const DEFAULT_SAMPLES: usize = 32;
+
+    pub fn new() -> Self {
+        Self::seeded(thread_rng().gen())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            samples: Self::DEFAULT_SAMPLES,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn with_samples(mut self, samples: usize) -> Self {
+        assert!(samples > 0, "At least one sample is needed");
+        self.samples = samples;
+        self
+    }
+
+    // Taking a win matters most, then blocking one, then how many lines the space could
+    // still help complete.
+    fn score(grid: &Grid, coordinate: &Coordinate, player: &Player) -> usize {
+        if grid.wins_with(coordinate, player) {
+            100
+        } else if player.opponents().any(|p| grid.wins_with(coordinate, &p)) {
+            50
+        } else {
+            grid.lines_through(coordinate)
+                .filter(|line| {
+                    line.get_spaces()
+                        .iter()
+                        .all(|s| s.get_player().is_none_or(|p| p == *player))
+                })
+                .count()
+        }
+    }
+
+    // One board the opponent could actually have, given the observation.
+    fn sample(&mut self, observation: &Observation) -> Grid {
+        let mut grid = observation.get_grid().clone();
+        let opponent = observation.get_player().turn();
+        let unknown = grid.legal_moves();
+
+        for coordinate in unknown.choose_multiple(&mut self.rng, observation.get_hidden()) {
+            grid.set_space(coordinate, &opponent).ok();
+        }
+        grid
+    }
+}
+
+impl Default for BeliefPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhantomPlayable for BeliefPlayer {
+    fn play(&mut self, observation: &Observation) -> Coordinate {
+        let player = observation.get_player();
+        let legal_moves = observation.get_grid().legal_moves();
+        let mut scores = vec![0; legal_moves.len()];
+
+        for _ in 0..self.samples {
+            let sample = self.sample(observation);
+            for (coordinate, score) in legal_moves.iter().zip(scores.iter_mut()) {
+                // Spaces that are taken in this sample would just be turned away.
+                if sample.is_legal(coordinate) {
+                    *score += Self::score(&sample, coordinate, &player);
+                }
+            }
+        }
+
+        // Ties go to the first space in board order, so a seeded player is reproducible.
+        let best = scores.iter().max().expect("No legal moves!");
+        legal_moves[scores.iter().position(|s| s == best).unwrap()]
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+#[cfg(test)]
+mod test_phantom {
+    use super::{play, play_seeded, BeliefPlayer, Observation, PhantomPlayable};
+    use crate::game::{Coordinate, Grid, Player, Space};
+    use crate::rando::RandoPlayer;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct ScriptedPlayer {
+        moves: Vec<Coordinate>,
+        observations: Rc<RefCell<Vec<Observation>>>,
+    }
+
+    impl ScriptedPlayer {
+        fn new(moves: &[&str]) -> Self {
+            ScriptedPlayer {
+                moves: moves.iter().map(|m| m.parse().unwrap()).collect(),
+                observations: Rc::new(RefCell::new(Vec::new())),
+            }
+        }
+    }
+
+    impl PhantomPlayable for ScriptedPlayer {
+        fn play(&mut self, observation: &Observation) -> Coordinate {
+            self.observations.borrow_mut().push(observation.clone());
+            self.moves.remove(0)
+        }
+    }
+
+    #[test]
+    fn rejects_hidden_spaces() {
+        // O runs into X on B2 and X runs into O on A1, before X wins on the diagonal.
+        let player_o = ScriptedPlayer::new(&["B2", "A1", "A2"]);
+        let observations = player_o.observations.clone();
+        let result = play(
+            Grid::empty(),
+            Box::new(ScriptedPlayer::new(&["B2", "A1", "C1", "A3"])),
+            Box::new(player_o),
+        );
+
+        assert_eq!(
+            &[
+                (Player::O, Coordinate::new(1, 1)),
+                (Player::X, Coordinate::new(0, 0)),
+            ],
+            result.get_attempts(),
+        );
+        assert_eq!(Some(Player::X), result.get_grid().get_winner());
+
+        let observations = observations.borrow();
+        assert_eq!(Player::O, observations[0].get_player());
+        assert_eq!(1, observations[0].get_hidden());
+        assert_eq!(
+            Space::Empty,
+            observations[0].get_grid().get_space(&Coordinate::new(1, 1)),
+        );
+
+        // B2 is revealed once O bumps into it, but X's C1 stays hidden.
+        assert_eq!(0, observations[1].get_hidden());
+        assert_eq!(
+            Space::X,
+            observations[1].get_grid().get_space(&Coordinate::new(1, 1)),
+        );
+        assert_eq!(1, observations[2].get_hidden());
+        assert_eq!(
+            Space::Empty,
+            observations[2].get_grid().get_space(&Coordinate::new(2, 0)),
+        );
+    }
+
+    #[test]
+    fn takes_winning_move() {
+        // X..
+        // .*.
+        // ..X
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::X).unwrap();
+        let observation = Observation {
+            player: Player::X,
+            grid,
+            hidden: 2,
+        };
+
+        let mut player = BeliefPlayer::seeded(1);
+        assert_eq!(Coordinate::new(1, 1), player.play(&observation));
+    }
+
+    #[test]
+    fn seeded_games_replay() {
+        let game = || {
+            play(
+                Grid::empty(),
+                Box::new(BeliefPlayer::seeded(3).with_samples(8)),
+                Box::new(BeliefPlayer::seeded(4).with_samples(8)),
+            )
+        };
+        assert_eq!(game(), game());
+
+        let game = |seed| {
+            play_seeded(
+                Grid::empty(),
+                Box::new(BeliefPlayer::new().with_samples(8)),
+                Box::new(RandoPlayer::new()),
+                seed,
+            )
+        };
+        assert_eq!(game(5), game(5));
+    }
+
+    #[test]
+    fn beats_random_player() {
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..20 {
+            let result = play_seeded(
+                Grid::empty(),
+                Box::new(BeliefPlayer::new().with_samples(8)),
+                Box::new(RandoPlayer::new()),
+                seed,
+            );
+            assert!(!result.get_grid().is_in_progress());
+            match result.get_grid().get_winner() {
+                Some(Player::X) => wins += 1,
+                Some(_) => losses += 1,
+                None => {}
+            }
+        }
+        assert!(wins > losses);
+    }
+}
@@ -0,0 +1,294 @@
+use super::rules::{self, Agent, Outcome, Rules};
+use super::{Grid, Playable, Player, Reply};
+
+// A two-player game under the swap (pie) rule: the first seat opens as X, then the
+// second seat may either reply as O or take over X's stone, leaving the first seat to
+// carry on as O. Seats are named by the symbol they open with, so `to_move` and the
+// outcome give X for the seat that opened and O for the seat offered the swap, whichever
+// symbol each ends up with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pie {
+    grid: Grid,
+}
+
+impl Pie {
+    pub fn new(grid: Grid) -> Self {
+        Self { grid }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    Opening,
+    Offer,
+    Play,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PieState {
+    grid: Grid,
+    seat: Player,
+    swapped: bool,
+    stage: Stage,
+}
+
+impl PieState {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn get_stage(&self) -> Stage {
+        self.stage
+    }
+
+    pub fn was_swapped(&self) -> bool {
+        self.swapped
+    }
+
+    // The symbol the seat to move plays with.
+    pub fn get_symbol(&self) -> Player {
+        self.symbol(&self.seat)
+    }
+
+    // The symbol a seat plays with. A swap trades symbols both ways, so this also finds
+    // the seat holding a symbol.
+    fn symbol(&self, seat: &Player) -> Player {
+        if self.swapped {
+            seat.turn()
+        } else {
+            *seat
+        }
+    }
+}
+
+impl Rules for Pie {
+    type State = PieState;
+    type Move = Reply;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> PieState {
+        PieState {
+            grid: self.grid.clone(),
+            seat: Player::X,
+            swapped: false,
+            stage: Stage::Opening,
+        }
+    }
+
+    fn to_move(&self, state: &PieState) -> Player {
+        state.seat
+    }
+
+    fn legal_moves(&self, state: &PieState) -> Vec<Reply> {
+        let plays = state.grid.legal_moves().into_iter().map(Reply::Play);
+        match state.stage {
+            Stage::Offer => std::iter::once(Reply::Swap).chain(plays).collect(),
+            _ => plays.collect(),
+        }
+    }
+
+    // Whoever holds O moves next after a swap, which is the seat that opened.
+    fn apply(&self, state: &PieState, chosen: &Reply) -> PieState {
+        let mut next = state.clone();
+        next.seat = state.seat.turn();
+        next.stage = match state.stage {
+            Stage::Opening => Stage::Offer,
+            _ => Stage::Play,
+        };
+
+        match chosen {
+            Reply::Swap => next.swapped = true,
+            Reply::Play(coordinate) => {
+                next.grid
+                    .set_space(coordinate, &state.get_symbol())
+                    .expect("Illegal move!");
+            }
+        }
+        next
+    }
+
+    fn outcome(&self, state: &PieState) -> Option<Outcome> {
+        match state.grid.get_winner() {
+            Some(winner) => Some(Outcome::Win(state.symbol(&winner))),
+            None if !state.grid.has_legal_moves() => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, seat: &Player) -> f64 {
+        outcome.payoff(seat)
+    }
+}
+
+// Seats a `Playable` under the swap rule, telling it when its symbol changes.
+struct PieSeat {
+    playable: Box<dyn Playable>,
+    symbol: Player,
+}
+
+impl Agent<Pie> for PieSeat {
+    fn play(&mut self, _rules: &Pie, state: &PieState) -> Reply {
+        let symbol = state.get_symbol();
+        if symbol != self.symbol {
+            self.playable.switch_sides(symbol);
+            self.symbol = symbol;
+        }
+
+        match state.stage {
+            Stage::Offer => self.playable.reply_to_opening(&state.grid),
+            _ => Reply::Play(Playable::play(self.playable.as_mut(), &state.grid)),
+        }
+    }
+}
+
+// Plays a game under the swap rule, `first` opening as X.
+pub fn play(grid: Grid, first: Box<dyn Playable>, second: Box<dyn Playable>) -> PieResult {
+    let seat = |playable, symbol| -> Box<dyn Agent<Pie>> { Box::new(PieSeat { playable, symbol }) };
+    let record = rules::play(
+        &Pie::new(grid),
+        vec![
+            (Player::X, seat(first, Player::X)),
+            (Player::O, seat(second, Player::O)),
+        ],
+    );
+
+    let state = record.get_state();
+    let symbols = if state.swapped {
+        [Player::O, Player::X]
+    } else {
+        [Player::X, Player::O]
+    };
+    PieResult {
+        grid: state.grid.clone(),
+        symbols,
+        swapped: state.swapped,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieResult {
+    grid: Grid,
+    symbols: [Player; 2],
+    swapped: bool,
+}
+
+impl PieResult {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn was_swapped(&self) -> bool {
+        self.swapped
+    }
+
+    // The symbol each seat finished the game with, first seat first.
+    pub fn get_symbols(&self) -> [Player; 2] {
+        self.symbols
+    }
+
+    // 0 for the seat that opened, 1 for the seat that was offered the swap.
+    pub fn get_winning_seat(&self) -> Option<usize> {
+        let winner = self.grid.get_winner()?;
+        self.symbols.iter().position(|symbol| *symbol == winner)
+    }
+}
+
+#[cfg(test)]
+mod test_pie {
+    use super::{play, Grid, Pie, Playable, Player, Reply};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Coordinate, Space};
+    use crate::rules::{self, MinimaxPlayer, Outcome};
+
+    struct ScriptedPlayer {
+        moves: Vec<Coordinate>,
+        swap: bool,
+    }
+
+    impl ScriptedPlayer {
+        fn new(moves: &[&str], swap: bool) -> Self {
+            ScriptedPlayer {
+                moves: moves.iter().map(|m| m.parse().unwrap()).collect(),
+                swap,
+            }
+        }
+    }
+
+    impl Playable for ScriptedPlayer {
+        fn play(&mut self, _grid: &Grid) -> Coordinate {
+            self.moves.remove(0)
+        }
+
+        fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+            if self.swap {
+                Reply::Swap
+            } else {
+                Reply::Play(self.play(grid))
+            }
+        }
+    }
+
+    #[test]
+    fn without_swap() {
+        // X X X
+        // O O .
+        // . . .
+        let result = play(
+            Grid::empty(),
+            Box::new(ScriptedPlayer::new(&["A1", "B1", "C1"], false)),
+            Box::new(ScriptedPlayer::new(&["A2", "B2"], false)),
+        );
+
+        assert!(!result.was_swapped());
+        assert_eq!([Player::X, Player::O], result.get_symbols());
+        assert_eq!(Some(0), result.get_winning_seat());
+    }
+
+    #[test]
+    fn with_swap() {
+        // The second seat takes over A1 as X, so the first seat moves next as O.
+        // X O .
+        // X O .
+        // X . .
+        let result = play(
+            Grid::empty(),
+            Box::new(ScriptedPlayer::new(&["A1", "B1", "B2"], false)),
+            Box::new(ScriptedPlayer::new(&["A2", "A3"], true)),
+        );
+
+        assert!(result.was_swapped());
+        assert_eq!([Player::O, Player::X], result.get_symbols());
+        assert_eq!(
+            Space::O,
+            result.get_grid().get_space(&"B1".parse().unwrap())
+        );
+        assert_eq!(Some(Player::X), result.get_grid().get_winner());
+        assert_eq!(Some(1), result.get_winning_seat());
+    }
+
+    #[test]
+    fn computer_players() {
+        let result = play(
+            Grid::empty(),
+            Box::new(ComputerPlayer::new_silent(Player::X)),
+            Box::new(ComputerPlayer::new_silent(Player::O)),
+        );
+
+        let grid = result.get_grid();
+        assert!(!grid.is_in_progress());
+        assert!(grid.count(&Player::X) - grid.count(&Player::O) <= 1);
+    }
+
+    #[test]
+    fn generic_rules() {
+        // Tic-tac-toe is a draw with or without the swap.
+        let record = rules::play(
+            &Pie::new(Grid::empty()),
+            vec![
+                (Player::X, Box::new(MinimaxPlayer::new())),
+                (Player::O, Box::new(MinimaxPlayer::new())),
+            ],
+        );
+        assert_eq!(&Outcome::Draw, record.get_outcome());
+    }
+}
@@ -0,0 +1,442 @@
+use super::computer::ComputerPlayer;
+use super::rando::{random_move, RandoPlayer};
+use super::symmetry::canonical;
+use super::{play, Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Learns the value of each move in each position of classic tic-tac-toe by Q-learning:
+// after every game, each move is nudged towards the game's result if it was the last
+// one, or towards the best value on offer at the player's next turn otherwise. Games are
+// played through `lib::play()`, with the learner taking ε-greedy moves against opponents
+// drawn from a pool, and positions that are turns of each other share their values.
+//
+// Everything random in training comes from one seeded generator, so the same seed and
+// settings always learn the same table. Evaluation games seed `ComputerPlayer` by game
+// number, so the curve is repeatable too.
+pub struct Trainer {
+    shared: Rc<RefCell<Shared>>,
+    learning_rate: f64,
+    discount: f64,
+    opponents: Vec<Opponent>,
+    snapshot: QTable,
+    games: usize,
+}
+
+// Who the learner plays against. One is picked at random for each game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opponent {
+    // The learner plays both sides, and learns from both.
+    SelfPlay,
+    // Uniformly random moves
+    Random,
+    // A greedy player on the table as it was at the last snapshot
+    Snapshot,
+}
+
+// The values of moves, by canonical position and the move's space on the canonical board.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QTable {
+    values: HashMap<(String, usize), f64>,
+}
+
+// Plays the best move in the table, and the first of them in board order on a tie.
+#[derive(Debug, Clone)]
+pub struct QPlayer {
+    table: QTable,
+}
+
+// How the greedy player did against `ComputerPlayer` after a number of training games,
+// counting games from both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurvePoint {
+    games: usize,
+    wins: usize,
+    draws: usize,
+    losses: usize,
+}
+
+struct Shared {
+    table: QTable,
+    rng: StdRng,
+    exploration: f64,
+    // Each move of the game in progress: who made it, from which canonical position,
+    // which canonical space, and the canonical spaces that were open
+    moves: Vec<(Player, String, usize, Vec<usize>)>,
+}
+
+// A seat the trainer is learning for.
+struct Learner {
+    player: Player,
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl Trainer {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            shared: Rc::new(RefCell::new(Shared {
+                table: QTable::default(),
+                rng: StdRng::seed_from_u64(seed),
+                exploration: 0.1,
+                moves: Vec::new(),
+            })),
+            learning_rate: 0.3,
+            discount: 0.95,
+            opponents: vec![Opponent::SelfPlay, Opponent::Random],
+            snapshot: QTable::default(),
+            games: 0,
+        }
+    }
+
+    pub fn with_learning_rate(mut self, learning_rate: f64) -> Self {
+        self.learning_rate = learning_rate;
+        self
+    }
+
+    pub fn with_discount(mut self, discount: f64) -> Self {
+        self.discount = discount;
+        self
+    }
+
+    // The chance of the learner trying a random move instead of its best one.
+    pub fn with_exploration(self, exploration: f64) -> Self {
+        self.shared.borrow_mut().exploration = exploration;
+        self
+    }
+
+    pub fn with_opponents(mut self, opponents: Vec<Opponent>) -> Self {
+        assert!(
+            !opponents.is_empty(),
+            "The pool needs at least one opponent"
+        );
+        self.opponents = opponents;
+        self
+    }
+
+    pub fn get_table(&self) -> QTable {
+        self.shared.borrow().table.clone()
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    // A greedy player on the table as it stands.
+    pub fn player(&self) -> QPlayer {
+        QPlayer::new(self.get_table())
+    }
+
+    // Takes a copy of the table for `Opponent::Snapshot` to play.
+    pub fn snapshot(&mut self) {
+        self.snapshot = self.get_table();
+    }
+
+    pub fn train(&mut self, games: usize) {
+        for _ in 0..games {
+            self.play_one();
+        }
+    }
+
+    // Trains in stages of `every` games, taking a snapshot and playing `evaluation`
+    // games against `ComputerPlayer` from each side after every stage.
+    pub fn train_with_curve(
+        &mut self,
+        games: usize,
+        every: usize,
+        evaluation: usize,
+    ) -> Vec<CurvePoint> {
+        assert!(every > 0, "Stages need at least one game");
+
+        let mut curve = Vec::new();
+        let mut trained = 0;
+        while trained < games {
+            let stage = every.min(games - trained);
+            self.train(stage);
+            trained += stage;
+            self.snapshot();
+            curve.push(self.evaluate(evaluation));
+        }
+        curve
+    }
+
+    // Plays the greedy player against `ComputerPlayer`, `games` times from each side.
+    pub fn evaluate(&self, games: usize) -> CurvePoint {
+        let mut point = CurvePoint {
+            games: self.games,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        };
+
+        for player in [Player::X, Player::O].iter() {
+            for game in 0..games {
+                let learner: Box<dyn Playable> = Box::new(self.player());
+                let computer: Box<dyn Playable> =
+                    Box::new(ComputerPlayer::new_silent(player.turn()).with_seed(game as u64));
+                let grid = match player {
+                    Player::X => play(learner, computer),
+                    _ => play(computer, learner),
+                };
+
+                match grid.get_winner() {
+                    Some(winner) if winner == *player => point.wins += 1,
+                    Some(_) => point.losses += 1,
+                    None => point.draws += 1,
+                }
+            }
+        }
+
+        point
+    }
+
+    fn play_one(&mut self) {
+        // The learner takes turns playing X and O against the pool.
+        let learner = [Player::X, Player::O][self.games % 2];
+        let opponent = {
+            let mut shared = self.shared.borrow_mut();
+            shared.moves.clear();
+            *self.opponents.choose(&mut shared.rng).unwrap()
+        };
+
+        let seat = |player: Player| -> Box<dyn Playable> {
+            Box::new(Learner {
+                player,
+                shared: Rc::clone(&self.shared),
+            })
+        };
+        let other: Box<dyn Playable> = match opponent {
+            Opponent::SelfPlay => seat(learner.turn()),
+            Opponent::Random => Box::new(RandoPlayer::seeded(self.shared.borrow_mut().rng.gen())),
+            Opponent::Snapshot => Box::new(QPlayer::new(self.snapshot.clone())),
+        };
+
+        let grid = match learner {
+            Player::X => play(seat(Player::X), other),
+            _ => play(other, seat(Player::O)),
+        };
+
+        let learners = match opponent {
+            Opponent::SelfPlay => vec![Player::X, Player::O],
+            _ => vec![learner],
+        };
+        for player in learners {
+            let reward = match grid.get_winner() {
+                Some(winner) if winner == player => 1.0,
+                Some(_) => -1.0,
+                None => 0.0,
+            };
+            self.learn(&player, reward);
+        }
+
+        self.games += 1;
+    }
+
+    // Works back from the player's last move: it takes the game's result, and every move
+    // before it takes the best value available at the player's following turn.
+    fn learn(&mut self, player: &Player, reward: f64) {
+        let mut shared = self.shared.borrow_mut();
+        let moves: Vec<(String, usize, Vec<usize>)> = shared
+            .moves
+            .iter()
+            .filter(|(p, _, _, _)| p == player)
+            .map(|(_, key, space, open)| (key.clone(), *space, open.clone()))
+            .collect();
+
+        let mut target = reward;
+        for (key, space, open) in moves.into_iter().rev() {
+            let value = shared
+                .table
+                .values
+                .entry((key.clone(), space))
+                .or_insert(0.0);
+            *value += self.learning_rate * (target - *value);
+            target = self.discount * shared.table.best_value(&key, &open);
+        }
+    }
+}
+
+impl QTable {
+    // The value of playing on this space, from the mover's side: 1 for a sure win and -1
+    // for a sure loss. Moves it hasn't seen are worth 0.
+    pub fn value(&self, grid: &Grid, coordinate: &Coordinate) -> f64 {
+        let (key, symmetry) = canonical(grid);
+        let space = index(&symmetry.apply(coordinate, grid.width()), grid.width());
+        self.values.get(&(key, space)).copied().unwrap_or(0.0)
+    }
+
+    // Every legal move with the highest value, in board order.
+    pub fn best_moves(&self, grid: &Grid) -> Vec<Coordinate> {
+        let values: Vec<(Coordinate, f64)> = grid
+            .legal_moves()
+            .into_iter()
+            .map(|c| (c, self.value(grid, &c)))
+            .collect();
+        let best = values
+            .iter()
+            .map(|(_, value)| *value)
+            .fold(f64::NEG_INFINITY, f64::max);
+        values
+            .into_iter()
+            .filter(|(_, value)| *value == best)
+            .map(|(c, _)| c)
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn best_value(&self, key: &str, open: &[usize]) -> f64 {
+        open.iter()
+            .map(|space| {
+                self.values
+                    .get(&(key.to_string(), *space))
+                    .copied()
+                    .unwrap_or(0.0)
+            })
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+}
+
+impl QPlayer {
+    pub fn new(table: QTable) -> Self {
+        Self { table }
+    }
+}
+
+impl Playable for QPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        *self
+            .table
+            .best_moves(grid)
+            .first()
+            .expect("No legal moves!")
+    }
+}
+
+impl CurvePoint {
+    // Training games played before this point
+    pub fn get_games(&self) -> usize {
+        self.games
+    }
+
+    pub fn get_wins(&self) -> usize {
+        self.wins
+    }
+
+    pub fn get_draws(&self) -> usize {
+        self.draws
+    }
+
+    pub fn get_losses(&self) -> usize {
+        self.losses
+    }
+}
+
+impl Playable for Learner {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        let mut shared = self.shared.borrow_mut();
+        let size = grid.width();
+        let (key, symmetry) = canonical(grid);
+        let open: Vec<usize> = grid
+            .legal_moves()
+            .iter()
+            .map(|c| index(&symmetry.apply(c, size), size))
+            .collect();
+
+        let exploration = shared.exploration;
+        let coordinate = if shared.rng.gen_bool(exploration) {
+            random_move(grid, &mut shared.rng).expect("No legal moves!")
+        } else {
+            let best = shared.table.best_moves(grid);
+            *best.choose(&mut shared.rng).expect("No legal moves!")
+        };
+
+        let space = index(&symmetry.apply(&coordinate, size), size);
+        shared.moves.push((self.player, key, space, open));
+        coordinate
+    }
+}
+
+fn index(coordinate: &Coordinate, size: usize) -> usize {
+    coordinate.y() * size + coordinate.x()
+}
+
+#[cfg(test)]
+mod test_q_learning {
+    use super::{Opponent, QTable, Trainer};
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::Playable;
+
+    #[test]
+    fn seeded_training_replays() {
+        let table = |seed| {
+            let mut trainer = Trainer::new(seed).with_opponents(vec![
+                Opponent::SelfPlay,
+                Opponent::Random,
+                Opponent::Snapshot,
+            ]);
+            trainer.train(200);
+            trainer.snapshot();
+            trainer.train(200);
+            trainer.get_table()
+        };
+
+        let first: QTable = table(1);
+        assert!(!first.is_empty());
+        assert_eq!(first, table(1));
+        assert_ne!(first, table(2));
+    }
+
+    #[test]
+    fn learns_to_win_and_block() {
+        let mut trainer = Trainer::new(1);
+        trainer.train(5000);
+        let mut player = trainer.player();
+
+        // XX.
+        // OO.
+        // ...
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(1, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+        assert_eq!(Coordinate::new(2, 0), player.play(&grid));
+
+        // X..
+        // XO.
+        // O..
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 2), &Player::O).unwrap();
+        assert_eq!(Coordinate::new(2, 0), player.play(&grid));
+    }
+
+    #[test]
+    fn learning_curve() {
+        let mut trainer = Trainer::new(4);
+        let curve = trainer.train_with_curve(250, 100, 5);
+
+        assert_eq!(
+            vec![100, 200, 250],
+            curve.iter().map(|p| p.get_games()).collect::<Vec<_>>(),
+        );
+        for point in curve.iter() {
+            assert_eq!(
+                10,
+                point.get_wins() + point.get_draws() + point.get_losses()
+            );
+        }
+        assert_eq!(250, trainer.games());
+    }
+}
@@ -1,28 +1,70 @@
-use super::{Coordinate, Grid, Playable};
+use super::rules::{Agent, Rules};
+use super::{Coordinate, Grid, Playable, Reply};
 use rand::prelude::*;
+use rand::rngs::StdRng;
 
 pub struct RandoPlayer {
-    rng: ThreadRng,
+    rng: StdRng,
 }
 
 impl RandoPlayer {
     pub fn new() -> Self {
-        Self { rng: thread_rng() }
+        Self::seeded(thread_rng().gen())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Picks one of the options at random, for games whose moves aren't just coordinates.
+    pub fn choose<'a, T>(&mut self, options: &'a [T]) -> Option<&'a T> {
+        options.choose(&mut self.rng)
+    }
+}
+
+// A uniformly random legal move, as `RandoPlayer` plays. Other players use this for
+// random moves with their own, possibly seeded, random number generators.
+pub fn random_move<R: Rng>(grid: &Grid, rng: &mut R) -> Option<Coordinate> {
+    grid.legal_moves().choose(rng).copied()
+}
+
+// The same, under any rules. `MctsPlayer` plays out its random games with this.
+pub fn random_rules_move<R: Rules, G: Rng>(
+    rules: &R,
+    state: &R::State,
+    rng: &mut G,
+) -> Option<R::Move> {
+    rules.legal_moves(state).choose(rng).cloned()
+}
+
+impl Default for RandoPlayer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl Playable for RandoPlayer {
     fn play(&mut self, grid: &Grid) -> Coordinate {
-        let mut legal_moves = Vec::with_capacity(9);
-        for x in 0..=2 {
-            for y in 0..=2 {
-                let coordinate = Coordinate::new(x, y);
-                if grid.is_legal(&coordinate) {
-                    legal_moves.push(coordinate);
-                }
-            }
+        random_move(grid, &mut self.rng).expect("No legal moves!")
+    }
+
+    fn reply_to_opening(&mut self, grid: &Grid) -> Reply {
+        if self.rng.gen() {
+            Reply::Swap
+        } else {
+            Reply::Play(Playable::play(self, grid))
         }
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
 
-        *legal_moves.choose(&mut self.rng).expect("No legal moves!")
+impl<R: Rules> Agent<R> for RandoPlayer {
+    fn play(&mut self, rules: &R, state: &R::State) -> R::Move {
+        random_rules_move(rules, state, &mut self.rng).expect("No legal moves!")
     }
 }
@@ -0,0 +1,308 @@
+use super::rules::{self, Agent, Outcome, Position, Rules, Seeded};
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+// Random-turn tic-tac-toe: instead of alternating, a fair coin decides who moves next.
+// The seed for the next toss is part of the state, and the first one is tossed from
+// `seed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomTurn {
+    grid: Grid,
+    seed: u64,
+}
+
+impl RandomTurn {
+    pub fn new(grid: Grid, seed: u64) -> Self {
+        Self { grid, seed }
+    }
+
+    // Tosses for the next mover, and seeds the state that follows from the same coin.
+    fn toss(rng: &mut StdRng, grid: Grid) -> Seeded {
+        let player = if rng.gen_bool(0.5) {
+            Player::X
+        } else {
+            Player::O
+        };
+        Seeded::new(Position::new(grid, player), rng.gen())
+    }
+}
+
+impl Rules for RandomTurn {
+    type State = Seeded;
+    type Move = Coordinate;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> Seeded {
+        Self::toss(&mut StdRng::seed_from_u64(self.seed), self.grid.clone())
+    }
+
+    fn to_move(&self, state: &Seeded) -> Player {
+        state.get_player()
+    }
+
+    fn legal_moves(&self, state: &Seeded) -> Vec<Coordinate> {
+        state.get_grid().legal_moves()
+    }
+
+    fn apply(&self, state: &Seeded, chosen: &Coordinate) -> Seeded {
+        let mut grid = state.get_grid().clone();
+        grid.set_space(chosen, &state.get_player())
+            .expect("Illegal move!");
+        Self::toss(&mut state.rng(), grid)
+    }
+
+    fn outcome(&self, state: &Seeded) -> Option<Outcome> {
+        match state.get_grid().get_winner() {
+            Some(winner) => Some(Outcome::Win(winner)),
+            None if !state.get_grid().has_legal_moves() => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, player: &Player) -> f64 {
+        outcome.payoff(player)
+    }
+}
+
+// Lets any `Playable` take a seat in a random-turn game.
+impl Agent<RandomTurn> for Box<dyn Playable> {
+    fn play(&mut self, _rules: &RandomTurn, state: &Seeded) -> Coordinate {
+        Playable::play(self.as_mut(), state.get_grid())
+    }
+}
+
+// Plays out a random-turn game. The seed decides every toss, so a game between
+// deterministic players can be replayed.
+pub fn play(
+    grid: Grid,
+    player_x: Box<dyn Playable>,
+    player_o: Box<dyn Playable>,
+    seed: u64,
+) -> RandomTurnResult {
+    let record = rules::play(
+        &RandomTurn::new(grid, seed),
+        vec![
+            (Player::X, Box::new(player_x)),
+            (Player::O, Box::new(player_o)),
+        ],
+    );
+
+    RandomTurnResult {
+        grid: record.get_state().get_grid().clone(),
+        movers: record
+            .get_moves()
+            .iter()
+            .map(|(player, _)| *player)
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RandomTurnResult {
+    grid: Grid,
+    movers: Vec<Player>,
+}
+
+impl RandomTurnResult {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    // Who the coin picked for each move, in order.
+    pub fn get_movers(&self) -> &[Player] {
+        &self.movers
+    }
+}
+
+// Plays to maximize its chance of winning, given that every future turn is a coin toss.
+// Between moves that win equally often, it picks the one that loses least often.
+#[derive(Clone)]
+pub struct RandomTurnPlayer {
+    player: Player,
+    // The chance of X winning and of O winning from each position, before the toss.
+    odds: HashMap<Grid, (f64, f64)>,
+}
+
+impl RandomTurnPlayer {
+    const EPSILON: f64 = 1e-12;
+
+    pub fn new(player: Player) -> Self {
+        assert!(
+            player == Player::X || player == Player::O,
+            "Random-turn games are for X and O only",
+        );
+        Self {
+            player,
+            odds: HashMap::new(),
+        }
+    }
+
+    // The chance of X winning and of O winning from a position, before the coin is tossed.
+    pub fn odds(&mut self, grid: &Grid) -> (f64, f64) {
+        if let Some(odds) = self.odds.get(grid) {
+            return *odds;
+        }
+
+        let odds = match grid.get_winner() {
+            Some(Player::X) => (1.0, 0.0),
+            Some(_) => (0.0, 1.0),
+            None if !grid.has_legal_moves() => (0.0, 0.0),
+            None => {
+                let (_, x_odds) = self.best_move(grid, &Player::X);
+                let (_, o_odds) = self.best_move(grid, &Player::O);
+                ((x_odds.0 + o_odds.0) / 2.0, (x_odds.1 + o_odds.1) / 2.0)
+            }
+        };
+
+        self.odds.insert(grid.clone(), odds);
+        odds
+    }
+
+    // The best move for a player who has won the toss, and the odds that follow from it.
+    fn best_move(&mut self, grid: &Grid, player: &Player) -> (Coordinate, (f64, f64)) {
+        let mut best: Option<(Coordinate, (f64, f64))> = None;
+
+        for coordinate in grid.legal_moves() {
+            let mut next_grid = grid.clone();
+            next_grid.set_space(&coordinate, player).ok();
+            let odds = self.odds(&next_grid);
+
+            let (win, loss) = if *player == Player::X {
+                odds
+            } else {
+                (odds.1, odds.0)
+            };
+            let is_better = match best {
+                None => true,
+                Some((_, best_odds)) => {
+                    let (best_win, best_loss) = if *player == Player::X {
+                        best_odds
+                    } else {
+                        (best_odds.1, best_odds.0)
+                    };
+                    win > best_win + Self::EPSILON
+                        || (win > best_win - Self::EPSILON && loss < best_loss - Self::EPSILON)
+                }
+            };
+
+            if is_better {
+                best = Some((coordinate, odds));
+            }
+        }
+
+        best.expect("No legal moves!")
+    }
+}
+
+impl Playable for RandomTurnPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        let player = self.player;
+        self.best_move(grid, &player).0
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+}
+
+#[cfg(test)]
+mod test_random_turn {
+    use super::{play, Playable, RandomTurnPlayer};
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+
+    #[test]
+    fn coin_decides_movers() {
+        let mut player = RandomTurnPlayer::new(Player::X);
+        player.odds(&Grid::empty());
+        let mut player_o = player.clone();
+        player_o.switch_sides(Player::O);
+
+        let result = play(
+            Grid::empty(),
+            Box::new(player.clone()),
+            Box::new(player_o.clone()),
+            5,
+        );
+        let grid = result.get_grid();
+        let movers = result.get_movers();
+
+        assert!(!grid.is_in_progress());
+        assert_eq!(
+            grid.count(&Player::X),
+            movers.iter().filter(|p| **p == Player::X).count(),
+        );
+        assert_eq!(
+            grid.count(&Player::O),
+            movers.iter().filter(|p| **p == Player::O).count(),
+        );
+
+        let replay = play(Grid::empty(), Box::new(player), Box::new(player_o), 5);
+        assert_eq!(result, replay);
+    }
+
+    #[test]
+    fn game_is_fair() {
+        // Nobody moves first in a random-turn game, so the odds are even.
+        let mut player = RandomTurnPlayer::new(Player::X);
+        let (x_odds, o_odds) = player.odds(&Grid::empty());
+        assert!((x_odds - o_odds).abs() < 1e-9);
+        assert!(x_odds + o_odds <= 1.0);
+        assert!(x_odds > 0.0);
+    }
+
+    #[test]
+    fn takes_winning_move() {
+        // X..
+        // .*.
+        // ..X
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+
+        let mut player = RandomTurnPlayer::new(Player::X);
+        assert_eq!(Coordinate::new(1, 1), player.play(&grid));
+
+        grid.set_space(&Coordinate::new(1, 1), &Player::X).unwrap();
+        assert_eq!((1.0, 0.0), player.odds(&grid));
+    }
+
+    #[test]
+    fn blocks_when_it_cannot_win() {
+        // O..
+        // .*.
+        // ..O
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::O).unwrap();
+
+        let mut player = RandomTurnPlayer::new(Player::X);
+        assert_eq!(Coordinate::new(1, 1), player.play(&grid));
+    }
+
+    #[test]
+    fn beats_random_player() {
+        let mut player = RandomTurnPlayer::new(Player::X);
+        player.odds(&Grid::empty());
+
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..20 {
+            let result = play(
+                Grid::empty(),
+                Box::new(player.clone()),
+                Box::new(RandoPlayer::seeded(seed)),
+                seed,
+            );
+            match result.get_grid().get_winner() {
+                Some(Player::X) => wins += 1,
+                Some(_) => losses += 1,
+                None => {}
+            }
+        }
+        assert!(wins > losses);
+    }
+}
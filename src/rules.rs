@@ -0,0 +1,580 @@
+use super::game::{Coordinate, Grid, Player};
+use super::{Playable, TurnStructure, WinRule};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+// The rules of a turn-based game, written once so the game loop and players can be shared
+// between variants. Rules never change the state they're given; `apply` hands back the
+// state after the move instead.
+pub trait Rules {
+    type State: Clone + Eq + Hash;
+    type Move: Clone + PartialEq + fmt::Debug;
+    type Outcome: Clone + PartialEq + fmt::Debug;
+
+    fn initial_state(&self) -> Self::State;
+
+    fn to_move(&self, state: &Self::State) -> Player;
+
+    fn legal_moves(&self, state: &Self::State) -> Vec<Self::Move>;
+
+    fn apply(&self, state: &Self::State, chosen: &Self::Move) -> Self::State;
+
+    // How the game ended, or `None` while it's still going.
+    fn outcome(&self, state: &Self::State) -> Option<Self::Outcome>;
+
+    // How good an outcome is for a player, from -1 for a loss to 1 for a win.
+    fn payoff(&self, outcome: &Self::Outcome, player: &Player) -> f64;
+}
+
+// Anything that can pick a move under a given set of rules.
+pub trait Agent<R: Rules> {
+    fn play(&mut self, rules: &R, state: &R::State) -> R::Move;
+}
+
+// Plays a game out from the rules' initial state. Each seat moves whenever the rules say
+// it's their symbol's turn.
+pub fn play<R: Rules>(rules: &R, mut seats: Vec<(Player, Box<dyn Agent<R>>)>) -> Record<R> {
+    let mut state = rules.initial_state();
+    let mut moves = Vec::new();
+
+    loop {
+        if let Some(outcome) = rules.outcome(&state) {
+            return Record {
+                state,
+                moves,
+                outcome,
+            };
+        }
+
+        let player = rules.to_move(&state);
+        let (_, agent) = seats
+            .iter_mut()
+            .find(|(p, _)| *p == player)
+            .expect("Nobody is playing that symbol!");
+        let chosen = agent.play(rules, &state);
+
+        assert!(rules.legal_moves(&state).contains(&chosen), "Illegal move!");
+        state = rules.apply(&state, &chosen);
+        moves.push((player, chosen));
+    }
+}
+
+pub struct Record<R: Rules> {
+    state: R::State,
+    moves: Vec<(Player, R::Move)>,
+    outcome: R::Outcome,
+}
+
+impl<R: Rules> Record<R> {
+    // The state the game ended in.
+    pub fn get_state(&self) -> &R::State {
+        &self.state
+    }
+
+    pub fn get_moves(&self) -> &[(Player, R::Move)] {
+        &self.moves
+    }
+
+    pub fn get_outcome(&self) -> &R::Outcome {
+        &self.outcome
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Win(Player),
+    Draw,
+}
+
+impl Outcome {
+    pub fn payoff(&self, player: &Player) -> f64 {
+        match self {
+            Outcome::Win(winner) if winner == player => 1.0,
+            Outcome::Win(_) => -1.0,
+            Outcome::Draw => 0.0,
+        }
+    }
+}
+
+// A board together with whose turn it is. When a turn places more than one stone, it
+// also keeps how many the player has left to place, and under elimination who has
+// already completed a line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    grid: Grid,
+    player: Player,
+    stones: usize,
+    placings: Vec<Player>,
+}
+
+impl Position {
+    pub fn new(grid: Grid, player: Player) -> Self {
+        Self {
+            grid,
+            player,
+            stones: 1,
+            placings: Vec::new(),
+        }
+    }
+
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    // How many stones the player still has to place this turn, including the next one.
+    pub fn get_stones(&self) -> usize {
+        self.stones
+    }
+
+    // Players who have completed a line, in the order they did.
+    pub fn get_placings(&self) -> &[Player] {
+        &self.placings
+    }
+
+    // The position after a one-stone turn, with the turn passed to `next`.
+    pub fn next(&self, grid: Grid, next: Player) -> Self {
+        Self {
+            grid,
+            player: next,
+            stones: 1,
+            placings: self.placings.clone(),
+        }
+    }
+}
+
+// A position in a game of chance, together with the seed for its next roll. Keeping the
+// seed in the state means a move always rolls the same way, so `apply` stays a plain
+// function of the state and a game replays exactly from its first seed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Seeded {
+    position: Position,
+    seed: u64,
+}
+
+impl Seeded {
+    pub fn new(position: Position, seed: u64) -> Self {
+        Self { position, seed }
+    }
+
+    pub fn get_position(&self) -> &Position {
+        &self.position
+    }
+
+    pub fn get_grid(&self) -> &Grid {
+        self.position.get_grid()
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.position.get_player()
+    }
+
+    // The dice for the next roll. Seed the next state from them once they've been rolled.
+    pub fn rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.seed)
+    }
+}
+
+// k-in-a-row on any board: seats take turns claiming spaces, as many a turn as the turn
+// structure calls for, and the first to complete a winning set wins. Under elimination
+// the others play on for the places behind, and the outcome is a win for whoever placed
+// first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KInARow {
+    grid: Grid,
+    players: Vec<Player>,
+    win_rule: WinRule,
+    structure: TurnStructure,
+}
+
+impl KInARow {
+    pub fn new(grid: Grid, players: Vec<Player>) -> Self {
+        assert!(players.len() > 1, "A game needs at least two players");
+        Self {
+            grid,
+            players,
+            win_rule: WinRule::FirstToLine,
+            structure: TurnStructure::SINGLE,
+        }
+    }
+
+    // Tic-tac-toe itself
+    pub fn classic() -> Self {
+        Self::new(Grid::empty(), vec![Player::X, Player::O])
+    }
+
+    pub fn with_win_rule(mut self, win_rule: WinRule) -> Self {
+        self.win_rule = win_rule;
+        self
+    }
+
+    pub fn with_turns(mut self, structure: TurnStructure) -> Self {
+        self.structure = structure;
+        self
+    }
+
+    // The next seat after `player` that's still in the game.
+    fn next_player(&self, player: &Player, placings: &[Player]) -> Player {
+        let seat = self.players.iter().position(|p| p == player).unwrap();
+        (1..=self.players.len())
+            .map(|i| self.players[(seat + i) % self.players.len()])
+            .find(|p| !placings.contains(p))
+            .unwrap_or(*player)
+    }
+}
+
+impl Rules for KInARow {
+    type State = Position;
+    type Move = Coordinate;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> Position {
+        Position {
+            stones: self.structure.stones(0).min(self.grid.legal_moves().len()),
+            ..Position::new(self.grid.clone(), self.players[0])
+        }
+    }
+
+    fn to_move(&self, state: &Position) -> Player {
+        state.player
+    }
+
+    fn legal_moves(&self, state: &Position) -> Vec<Coordinate> {
+        state.grid.legal_moves()
+    }
+
+    // A turn ends early if one of its stones completes a line.
+    fn apply(&self, state: &Position, chosen: &Coordinate) -> Position {
+        let player = state.player;
+        let mut grid = state.grid.clone();
+        grid.set_space(chosen, &player).expect("Illegal move!");
+
+        let mut placings = state.placings.clone();
+        let won = grid.has_won(&player);
+        if won {
+            placings.push(player);
+            if self.win_rule == WinRule::Elimination && placings.len() + 1 == self.players.len() {
+                placings.push(self.next_player(&player, &placings));
+            }
+        }
+
+        if !won && state.stones > 1 && grid.has_legal_moves() {
+            return Position {
+                grid,
+                player,
+                stones: state.stones - 1,
+                placings,
+            };
+        }
+
+        Position {
+            player: self.next_player(&player, &placings),
+            stones: self.structure.stones(1).min(grid.legal_moves().len()),
+            grid,
+            placings,
+        }
+    }
+
+    fn outcome(&self, state: &Position) -> Option<Outcome> {
+        let (winner, finished) = match self.win_rule {
+            WinRule::FirstToLine => {
+                let winner = state.grid.get_winner();
+                (winner, winner.is_some())
+            }
+            WinRule::Elimination => (
+                state.placings.first().copied(),
+                state.placings.len() == self.players.len(),
+            ),
+        };
+
+        if finished || !state.grid.has_legal_moves() {
+            Some(winner.map_or(Outcome::Draw, Outcome::Win))
+        } else {
+            None
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, player: &Player) -> f64 {
+        outcome.payoff(player)
+    }
+}
+
+// Lets any `Playable` take a seat in a k-in-a-row game.
+impl Agent<KInARow> for Box<dyn Playable> {
+    fn play(&mut self, _rules: &KInARow, state: &Position) -> Coordinate {
+        Playable::play(self.as_mut(), state.get_grid())
+    }
+}
+
+// Searches the game tree for the move with the best payoff, assuming every player makes
+// the move best for themselves. That's plain minimax with two players, and max-n with
+// more: each position is scored for every symbol, and the player to move picks by their
+// own score. Results are remembered between moves. Games that can go on forever, like
+// Teeko, need a depth limit; positions at the limit count as a draw.
+#[derive(Debug, Clone)]
+pub struct MinimaxPlayer<R: Rules> {
+    depth: usize,
+    // The payoffs from each state, and how deep they were searched
+    values: HashMap<R::State, (Payoffs, usize)>,
+}
+
+// A payoff for each symbol, in the order of `Player::ALL`
+type Payoffs = [f64; 4];
+
+impl<R: Rules> MinimaxPlayer<R> {
+    // Later wins are worth a little less, so the player doesn't dawdle.
+    const DISCOUNT: f64 = 0.99;
+
+    pub fn new() -> Self {
+        Self {
+            depth: usize::MAX,
+            values: HashMap::new(),
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        assert!(depth > 0, "The search must look at least one move ahead");
+        self.depth = depth;
+        self
+    }
+
+    // The value of a state for the player about to move.
+    pub fn value(&mut self, rules: &R, state: &R::State, depth: usize) -> f64 {
+        self.payoffs(rules, state, depth).0[index(&rules.to_move(state))]
+    }
+
+    // The value of each legal move for the player making it, in the order the rules list
+    // them.
+    pub fn move_values(&mut self, rules: &R, state: &R::State) -> Vec<(R::Move, f64)> {
+        let player = index(&rules.to_move(state));
+        let depth = self.depth;
+        rules
+            .legal_moves(state)
+            .into_iter()
+            .map(|chosen| {
+                let value = self.move_payoffs(rules, state, &chosen, depth).0[player];
+                (chosen, value)
+            })
+            .collect()
+    }
+
+    // The payoffs from a state, and whether the search reached the end of every line of
+    // play, which makes them good at any depth.
+    fn payoffs(&mut self, rules: &R, state: &R::State, depth: usize) -> (Payoffs, bool) {
+        if let Some(outcome) = rules.outcome(state) {
+            let mut payoffs = [0.0; 4];
+            for (payoff, player) in payoffs.iter_mut().zip(Player::ALL.iter()) {
+                *payoff = rules.payoff(&outcome, player);
+            }
+            return (payoffs, true);
+        } else if depth == 0 {
+            return ([0.0; 4], false);
+        } else if let Some((payoffs, searched)) = self.values.get(state) {
+            if *searched >= depth {
+                return (*payoffs, *searched == usize::MAX);
+            }
+        }
+
+        let player = index(&rules.to_move(state));
+        let mut best: Option<Payoffs> = None;
+        let mut exact = true;
+        for chosen in rules.legal_moves(state) {
+            let (payoffs, exact_move) = self.move_payoffs(rules, state, &chosen, depth);
+            exact &= exact_move;
+            if best.is_none_or(|best| payoffs[player] > best[player]) {
+                best = Some(payoffs);
+            }
+        }
+
+        let payoffs = best.expect("No legal moves!");
+        let searched = if exact { usize::MAX } else { depth };
+        self.values.insert(state.clone(), (payoffs, searched));
+        (payoffs, exact)
+    }
+
+    fn move_payoffs(
+        &mut self,
+        rules: &R,
+        state: &R::State,
+        chosen: &R::Move,
+        depth: usize,
+    ) -> (Payoffs, bool) {
+        let (mut payoffs, exact) = self.payoffs(rules, &rules.apply(state, chosen), depth - 1);
+        for payoff in payoffs.iter_mut() {
+            *payoff *= Self::DISCOUNT;
+        }
+        (payoffs, exact)
+    }
+}
+
+fn index(player: &Player) -> usize {
+    Player::ALL.iter().position(|p| p == player).unwrap()
+}
+
+impl<R: Rules> Default for MinimaxPlayer<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Rules> Agent<R> for MinimaxPlayer<R> {
+    // Ties go to the first move the rules list, so the player is deterministic.
+    fn play(&mut self, rules: &R, state: &R::State) -> R::Move {
+        let mut best: Option<(R::Move, f64)> = None;
+
+        for (chosen, value) in self.move_values(rules, state) {
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_value)| value > *best_value)
+            {
+                best = Some((chosen, value));
+            }
+        }
+
+        best.expect("No legal moves!").0
+    }
+}
+
+#[cfg(test)]
+mod test_rules {
+    use super::{play, Agent, KInARow, MinimaxPlayer, Outcome, Position, Rules};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::Playable;
+
+    #[test]
+    fn k_in_a_row() {
+        let rules = KInARow::new(Grid::with_size(4, 4, 3), vec![Player::O, Player::Delta]);
+        let state = rules.initial_state();
+        assert_eq!(Player::O, rules.to_move(&state));
+        assert_eq!(16, rules.legal_moves(&state).len());
+
+        let state = rules.apply(&state, &Coordinate::new(0, 0));
+        assert_eq!(Player::Delta, rules.to_move(&state));
+        assert_eq!(1, state.get_stones());
+        assert_eq!(None, rules.outcome(&state));
+        assert_eq!(
+            Some(Player::O),
+            state
+                .get_grid()
+                .get_space(&Coordinate::new(0, 0))
+                .get_player(),
+        );
+    }
+
+    #[test]
+    fn outcome() {
+        let mut grid = Grid::empty();
+        for raw in ["A1", "B1", "C1"].iter() {
+            grid.set_space(&raw.parse().unwrap(), &Player::O).unwrap();
+        }
+        let rules = KInARow::classic();
+        let outcome = rules.outcome(&Position::new(grid, Player::X)).unwrap();
+
+        assert_eq!(Outcome::Win(Player::O), outcome);
+        assert_eq!(1.0, rules.payoff(&outcome, &Player::O));
+        assert_eq!(-1.0, rules.payoff(&outcome, &Player::X));
+        assert_eq!(0.0, Outcome::Draw.payoff(&Player::X));
+    }
+
+    #[test]
+    fn random_players() {
+        let record = play(
+            &KInARow::classic(),
+            vec![
+                (Player::X, Box::new(RandoPlayer::new())),
+                (Player::O, Box::new(RandoPlayer::new())),
+            ],
+        );
+
+        let grid = record.get_state().get_grid();
+        assert_eq!(grid.count(&Player::X), record.get_moves().len().div_ceil(2));
+        match record.get_outcome() {
+            Outcome::Win(winner) => assert!(grid.has_line(winner)),
+            Outcome::Draw => assert!(!grid.has_legal_moves()),
+        }
+    }
+
+    #[test]
+    fn playable_seats() {
+        let record = play(
+            &KInARow::classic(),
+            vec![
+                (
+                    Player::X,
+                    Box::new(Box::new(ComputerPlayer::new_silent(Player::X)) as Box<dyn Playable>),
+                ),
+                (Player::O, Box::new(MinimaxPlayer::new())),
+            ],
+        );
+
+        // Perfect play from O never loses, and punishes the computer's slips.
+        assert_ne!(&Outcome::Win(Player::X), record.get_outcome());
+    }
+
+    #[test]
+    fn minimax_takes_win() {
+        // X can win on C1 right away, or on C3 a move later.
+        let mut grid = Grid::empty();
+        grid.set_space(&"A1".parse().unwrap(), &Player::X).unwrap();
+        grid.set_space(&"B1".parse().unwrap(), &Player::X).unwrap();
+        grid.set_space(&"A2".parse().unwrap(), &Player::O).unwrap();
+        grid.set_space(&"B3".parse().unwrap(), &Player::O).unwrap();
+
+        let rules = KInARow::classic();
+        let mut player = MinimaxPlayer::new();
+        let state = Position::new(grid, Player::X);
+        assert_eq!(Coordinate::new(2, 0), player.play(&rules, &state));
+        assert!(player.value(&rules, &state, usize::MAX) > 0.0);
+    }
+
+    #[test]
+    fn minimax_never_loses() {
+        let rules = KInARow::classic();
+        let mut player = MinimaxPlayer::new();
+        assert_eq!(
+            0.0,
+            player.value(&rules, &rules.initial_state(), usize::MAX)
+        );
+
+        for seed in 0..10 {
+            let record = play(
+                &rules,
+                vec![
+                    (Player::X, Box::new(RandoPlayer::seeded(seed))),
+                    (Player::O, Box::new(MinimaxPlayer::new())),
+                ],
+            );
+            assert_ne!(&Outcome::Win(Player::X), record.get_outcome());
+        }
+    }
+
+    #[test]
+    fn minimax_three_players() {
+        // Δ wins with A1 and either C1 or D1. If X plays B1, O can only block one of
+        // them, so X takes one itself and leaves O to block the other.
+        let a1 = Coordinate::new(0, 0);
+        let mut grid = Grid::with_winning_sets(
+            4,
+            1,
+            vec![
+                vec![a1, Coordinate::new(2, 0)],
+                vec![a1, Coordinate::new(3, 0)],
+            ],
+        );
+        grid.set_space(&a1, &Player::Delta).unwrap();
+
+        let rules = KInARow::new(grid, vec![Player::X, Player::O, Player::Delta]);
+        let state = rules.initial_state();
+        let mut player = MinimaxPlayer::new();
+        assert_eq!(Coordinate::new(2, 0), player.play(&rules, &state));
+        assert_eq!(0.0, player.value(&rules, &state, usize::MAX));
+    }
+}
@@ -0,0 +1,108 @@
+use super::rules::{KInARow, MinimaxPlayer, Position, Rules};
+use super::{Coordinate, Grid, Player};
+
+// Solves two-player games on small boards exactly, by trying every line of play and
+// remembering the result for each position it has seen. It's the generic minimax search
+// on k-in-a-row, with the results reduced to a win, draw or loss. Anything much bigger
+// than 4x4 has too many positions to solve this way.
+#[derive(Debug, Clone, Default)]
+pub struct Solver {
+    minimax: MinimaxPlayer<KInARow>,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The result with best play on both sides, for the player about to move: 1 for a win,
+    // 0 for a draw and -1 for a loss.
+    pub fn value(&mut self, grid: &Grid, player: &Player) -> i8 {
+        let (rules, state) = Self::position(grid, player);
+        Self::result(self.minimax.value(&rules, &state, usize::MAX))
+    }
+
+    // The result of each legal move, for the player making it.
+    pub fn move_values(&mut self, grid: &Grid, player: &Player) -> Vec<(Coordinate, i8)> {
+        let (rules, state) = Self::position(grid, player);
+        self.minimax
+            .move_values(&rules, &state)
+            .into_iter()
+            .map(|(coordinate, value)| (coordinate, Self::result(value)))
+            .collect()
+    }
+
+    // Every move that keeps the best result available, in board order.
+    pub fn best_moves(&mut self, grid: &Grid, player: &Player) -> Vec<Coordinate> {
+        let move_values = self.move_values(grid, player);
+        let best = move_values.iter().map(|(_, value)| *value).max();
+        move_values
+            .into_iter()
+            .filter(|(_, value)| Some(*value) == best)
+            .map(|(coordinate, _)| coordinate)
+            .collect()
+    }
+
+    fn position(grid: &Grid, player: &Player) -> (KInARow, Position) {
+        assert!(!grid.has_captures(), "The solver doesn't handle captures");
+        let rules = KInARow::new(grid.clone(), vec![*player, player.turn()]);
+        let state = rules.initial_state();
+        (rules, state)
+    }
+
+    // Minimax values shrink with each move it takes to reach the result, so only the sign
+    // says who wins.
+    fn result(value: f64) -> i8 {
+        if value > 0.0 {
+            1
+        } else if value < 0.0 {
+            -1
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_solver {
+    use super::Solver;
+    use crate::game::{Coordinate, Grid, Player};
+
+    #[test]
+    fn tic_tac_toe_is_a_draw() {
+        let mut solver = Solver::new();
+        assert_eq!(0, solver.value(&Grid::empty(), &Player::X));
+
+        // Every opening holds the draw, but only the centre answers a corner.
+        assert_eq!(9, solver.best_moves(&Grid::empty(), &Player::X).len());
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        assert_eq!(
+            vec![Coordinate::new(1, 1)],
+            solver.best_moves(&grid, &Player::O),
+        );
+    }
+
+    #[test]
+    fn finds_forced_wins() {
+        // X.O
+        // ...
+        // X.O   X wins on A2, and anything else lets O win on C2.
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 0), &Player::O).unwrap();
+        grid.set_space(&Coordinate::new(0, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::O).unwrap();
+
+        let mut solver = Solver::new();
+        assert_eq!(1, solver.value(&grid, &Player::X));
+        assert_eq!(
+            vec![Coordinate::new(0, 1)],
+            solver.best_moves(&grid, &Player::X),
+        );
+        assert!(solver
+            .move_values(&grid, &Player::X)
+            .iter()
+            .any(|(_, value)| *value == -1));
+    }
+}
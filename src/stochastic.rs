@@ -0,0 +1,440 @@
+use super::rules::{self, Agent, Outcome, Position, Rules, Seeded};
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use std::collections::HashMap;
+
+// Stochastic tic-tac-toe: the chosen space is only marked with probability `p`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stochastic {
+    p: f64,
+    miss: Miss,
+}
+
+// What happens to a mark that doesn't land where it was aimed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Miss {
+    // The mark lands on a random empty neighbour, or the turn is lost if there isn't one.
+    Adjacent,
+
+    // The turn is lost.
+    LoseTurn,
+}
+
+impl Stochastic {
+    pub fn new(p: f64, miss: Miss) -> Self {
+        assert!(p > 0.0 && p <= 1.0, "p must be in (0, 1]");
+        Self { p, miss }
+    }
+
+    // Every space the mark could end up on, with its probability. `None` is a lost turn.
+    pub fn outcomes(&self, grid: &Grid, coordinate: &Coordinate) -> Vec<(Option<Coordinate>, f64)> {
+        let mut outcomes = vec![(Some(*coordinate), self.p)];
+        let miss = 1.0 - self.p;
+
+        if miss > 0.0 {
+            let neighbours: Vec<Coordinate> = match self.miss {
+                Miss::Adjacent => grid
+                    .neighbours(coordinate)
+                    .into_iter()
+                    .filter(|c| grid.is_legal(c))
+                    .collect(),
+                Miss::LoseTurn => Vec::new(),
+            };
+
+            if neighbours.is_empty() {
+                outcomes.push((None, miss));
+            } else {
+                let share = miss / neighbours.len() as f64;
+                outcomes.extend(neighbours.into_iter().map(|c| (Some(c), share)));
+            }
+        }
+
+        outcomes
+    }
+
+    // Rolls for where a mark aimed at the coordinate actually lands.
+    pub fn resolve<R: Rng>(
+        &self,
+        grid: &Grid,
+        coordinate: &Coordinate,
+        rng: &mut R,
+    ) -> Option<Coordinate> {
+        let outcomes = self.outcomes(grid, coordinate);
+        outcomes
+            .choose_weighted(rng, |(_, p)| *p)
+            .expect("No outcomes!")
+            .0
+    }
+}
+
+// A stochastic game under the generic rules, from a starting board and the seed for the
+// first roll.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StochasticGame {
+    grid: Grid,
+    rules: Stochastic,
+    seed: u64,
+}
+
+impl StochasticGame {
+    pub fn new(grid: Grid, rules: Stochastic, seed: u64) -> Self {
+        Self { grid, rules, seed }
+    }
+}
+
+impl Rules for StochasticGame {
+    type State = Seeded;
+    type Move = Coordinate;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> Seeded {
+        Seeded::new(Position::new(self.grid.clone(), Player::X), self.seed)
+    }
+
+    fn to_move(&self, state: &Seeded) -> Player {
+        state.get_player()
+    }
+
+    fn legal_moves(&self, state: &Seeded) -> Vec<Coordinate> {
+        state.get_grid().legal_moves()
+    }
+
+    fn apply(&self, state: &Seeded, chosen: &Coordinate) -> Seeded {
+        let player = state.get_player();
+        let mut rng = state.rng();
+        let mut grid = state.get_grid().clone();
+
+        if let Some(landed) = self.rules.resolve(&grid, chosen, &mut rng) {
+            grid.set_space(&landed, &player).expect("Illegal move!");
+        }
+
+        Seeded::new(state.get_position().next(grid, player.turn()), rng.gen())
+    }
+
+    fn outcome(&self, state: &Seeded) -> Option<Outcome> {
+        match state.get_grid().get_winner() {
+            Some(winner) => Some(Outcome::Win(winner)),
+            None if !state.get_grid().has_legal_moves() => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, player: &Player) -> f64 {
+        outcome.payoff(player)
+    }
+}
+
+// Lets any `Playable` aim marks in a stochastic game.
+impl Agent<StochasticGame> for Box<dyn Playable> {
+    fn play(&mut self, _rules: &StochasticGame, state: &Seeded) -> Coordinate {
+        Playable::play(self.as_mut(), state.get_grid())
+    }
+}
+
+// Plays out a stochastic game. The seed decides every roll, so a game between
+// deterministic players can be replayed exactly.
+pub fn play(
+    grid: Grid,
+    rules: &Stochastic,
+    player_x: Box<dyn Playable>,
+    player_o: Box<dyn Playable>,
+    seed: u64,
+) -> Grid {
+    rules::play(
+        &StochasticGame::new(grid, *rules, seed),
+        vec![
+            (Player::X, Box::new(player_x)),
+            (Player::O, Box::new(player_o)),
+        ],
+    )
+    .get_state()
+    .get_grid()
+    .clone()
+}
+
+// Picks the move with the best expected result, treating every roll of the dice as a
+// chance node. Values are solved exactly and cached, so it's only practical on small
+// boards.
+#[derive(Clone)]
+pub struct ExpectiminimaxPlayer {
+    player: Player,
+    rules: Stochastic,
+    // Value of each position with X to move and with O to move, from X's point of view.
+    values: HashMap<Grid, (f64, f64)>,
+}
+
+impl ExpectiminimaxPlayer {
+    const EPSILON: f64 = 1e-12;
+
+    pub fn new(player: Player, rules: Stochastic) -> Self {
+        assert!(
+            player == Player::X || player == Player::O,
+            "Stochastic games are for X and O only",
+        );
+        Self {
+            player,
+            rules,
+            values: HashMap::new(),
+        }
+    }
+
+    // Expected result of aiming at a coordinate, from 1 (X wins) to -1 (O wins).
+    pub fn expected_value(&mut self, grid: &Grid, coordinate: &Coordinate, player: &Player) -> f64 {
+        let (placed, lost) = self.split_outcomes(grid, coordinate, player);
+        let (x_to_move, o_to_move) = self.values(grid);
+        let stay = if *player == Player::X {
+            o_to_move
+        } else {
+            x_to_move
+        };
+        placed + lost * stay
+    }
+
+    // Value of the position with X to move and with O to move.
+    pub fn values(&mut self, grid: &Grid) -> (f64, f64) {
+        if let Some(values) = self.values.get(grid) {
+            return *values;
+        }
+
+        let values = match grid.get_winner() {
+            Some(Player::X) => (1.0, 1.0),
+            Some(_) => (-1.0, -1.0),
+            None if !grid.has_legal_moves() => (0.0, 0.0),
+            None => {
+                let legal_moves = grid.legal_moves();
+                let x_moves: Vec<(f64, f64)> = legal_moves
+                    .iter()
+                    .map(|c| self.split_outcomes(grid, c, &Player::X))
+                    .collect();
+                let o_moves: Vec<(f64, f64)> = legal_moves
+                    .iter()
+                    .map(|c| self.split_outcomes(grid, c, &Player::O))
+                    .collect();
+
+                // A lost turn hands the same position to the other player, so the two
+                // values depend on each other. Each pass shrinks the error by (1 - p)^2.
+                let (mut x_value, mut o_value) = (0.0, 0.0);
+                loop {
+                    let next_x = x_moves
+                        .iter()
+                        .map(|(placed, lost)| placed + lost * o_value)
+                        .fold(f64::NEG_INFINITY, f64::max);
+                    let next_o = o_moves
+                        .iter()
+                        .map(|(placed, lost)| placed + lost * next_x)
+                        .fold(f64::INFINITY, f64::min);
+
+                    let converged = (next_x - x_value).abs() < Self::EPSILON
+                        && (next_o - o_value).abs() < Self::EPSILON;
+                    x_value = next_x;
+                    o_value = next_o;
+                    if converged {
+                        break;
+                    }
+                }
+                (x_value, o_value)
+            }
+        };
+
+        self.values.insert(grid.clone(), values);
+        values
+    }
+
+    // The expected value of the outcomes that place a mark, and the chance of losing the
+    // turn instead.
+    fn split_outcomes(
+        &mut self,
+        grid: &Grid,
+        coordinate: &Coordinate,
+        player: &Player,
+    ) -> (f64, f64) {
+        let mut placed = 0.0;
+        let mut lost = 0.0;
+
+        for (landed, p) in self.rules.outcomes(grid, coordinate) {
+            match landed {
+                Some(landed) => {
+                    let mut next_grid = grid.clone();
+                    next_grid.set_space(&landed, player).ok();
+                    let (x_to_move, o_to_move) = self.values(&next_grid);
+                    placed += p * if *player == Player::X {
+                        o_to_move
+                    } else {
+                        x_to_move
+                    };
+                }
+                None => lost += p,
+            }
+        }
+
+        (placed, lost)
+    }
+}
+
+impl Playable for ExpectiminimaxPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        let player = self.player;
+        let sign = if player == Player::X { 1.0 } else { -1.0 };
+
+        let mut best = None;
+        for coordinate in grid.legal_moves() {
+            let value = sign * self.expected_value(grid, &coordinate, &player);
+            match best {
+                Some((_, best_value)) if best_value >= value - Self::EPSILON => {}
+                _ => best = Some((coordinate, value)),
+            }
+        }
+
+        best.expect("No legal moves!").0
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+    }
+}
+
+#[cfg(test)]
+mod test_stochastic {
+    use super::{play, ExpectiminimaxPlayer, Miss, Playable, Stochastic};
+    use crate::game::{Coordinate, Grid, Player, Space};
+    use crate::rando::RandoPlayer;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn outcomes_adjacent() {
+        let rules = Stochastic::new(0.6, Miss::Adjacent);
+        let grid = Grid::new([
+            [Space::Empty, Space::X, Space::Empty],
+            [Space::O, Space::Empty, Space::Empty],
+            [Space::Empty, Space::Empty, Space::Empty],
+        ]);
+
+        let outcomes = rules.outcomes(&grid, &Coordinate::new(0, 0));
+        assert_eq!(2, outcomes.len());
+        assert_eq!((Some(Coordinate::new(0, 0)), 0.6), outcomes[0]);
+        assert_eq!(Some(Coordinate::new(1, 1)), outcomes[1].0);
+        assert!((outcomes[1].1 - 0.4).abs() < 1e-9);
+
+        let total: f64 = rules
+            .outcomes(&grid, &Coordinate::new(2, 2))
+            .iter()
+            .map(|(_, p)| p)
+            .sum();
+        assert!((total - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn outcomes_lose_turn() {
+        let rules = Stochastic::new(0.75, Miss::LoseTurn);
+        assert_eq!(
+            vec![(Some(Coordinate::new(1, 1)), 0.75), (None, 0.25)],
+            rules.outcomes(&Grid::empty(), &Coordinate::new(1, 1)),
+        );
+
+        // Nowhere to slip to
+        let rules = Stochastic::new(0.75, Miss::Adjacent);
+        let grid = Grid::new([
+            [Space::Empty, Space::X, Space::Empty],
+            [Space::O, Space::X, Space::Empty],
+            [Space::Empty, Space::Empty, Space::Empty],
+        ]);
+        assert_eq!(
+            vec![(Some(Coordinate::new(0, 0)), 0.75), (None, 0.25)],
+            rules.outcomes(&grid, &Coordinate::new(0, 0)),
+        );
+    }
+
+    #[test]
+    fn resolve_is_seeded() {
+        let rules = Stochastic::new(0.5, Miss::Adjacent);
+        let grid = Grid::empty();
+        let roll = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20)
+                .map(|_| rules.resolve(&grid, &Coordinate::new(1, 1), &mut rng))
+                .collect::<Vec<Option<Coordinate>>>()
+        };
+
+        assert_eq!(roll(7), roll(7));
+        assert!(roll(7).iter().all(|c| c.is_some()));
+        assert!(roll(7).iter().any(|c| *c != Some(Coordinate::new(1, 1))));
+    }
+
+    #[test]
+    fn deterministic_game_is_a_draw() {
+        let mut player = ExpectiminimaxPlayer::new(Player::X, Stochastic::new(1.0, Miss::LoseTurn));
+        let (x_to_move, _) = player.values(&Grid::empty());
+        assert!(x_to_move.abs() < 1e-9);
+    }
+
+    #[test]
+    fn first_move_advantage() {
+        let mut player = ExpectiminimaxPlayer::new(Player::X, Stochastic::new(0.5, Miss::LoseTurn));
+        let (x_to_move, o_to_move) = player.values(&Grid::empty());
+        assert!(x_to_move > 0.0);
+        assert!((x_to_move + o_to_move).abs() < 1e-9);
+    }
+
+    #[test]
+    fn takes_likely_win() {
+        // X..
+        // .*.
+        // ..X
+        let rules = Stochastic::new(0.8, Miss::Adjacent);
+        let mut grid = Grid::empty();
+        grid.set_space(&Coordinate::new(0, 0), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(2, 2), &Player::X).unwrap();
+        grid.set_space(&Coordinate::new(0, 1), &Player::O).unwrap();
+
+        let mut player = ExpectiminimaxPlayer::new(Player::X, rules);
+        assert_eq!(Coordinate::new(1, 1), player.play(&grid));
+    }
+
+    #[test]
+    fn replays_from_seed() {
+        let rules = Stochastic::new(0.7, Miss::Adjacent);
+        let mut player = ExpectiminimaxPlayer::new(Player::X, rules);
+        player.values(&Grid::empty());
+
+        let game = |seed| {
+            let mut player_o = player.clone();
+            player_o.switch_sides(Player::O);
+            play(
+                Grid::empty(),
+                &rules,
+                Box::new(player.clone()),
+                Box::new(player_o),
+                seed,
+            )
+        };
+
+        assert_eq!(game(42), game(42));
+        assert!(!game(42).is_in_progress());
+    }
+
+    #[test]
+    fn beats_random_player() {
+        let rules = Stochastic::new(0.7, Miss::Adjacent);
+        let mut player = ExpectiminimaxPlayer::new(Player::X, rules);
+        player.values(&Grid::empty());
+
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..20 {
+            let grid = play(
+                Grid::empty(),
+                &rules,
+                Box::new(player.clone()),
+                Box::new(RandoPlayer::seeded(seed)),
+                seed,
+            );
+            match grid.get_winner() {
+                Some(Player::X) => wins += 1,
+                Some(_) => losses += 1,
+                None => {}
+            }
+        }
+        assert!(wins > losses);
+    }
+}
@@ -0,0 +1,448 @@
+use super::file::FileError;
+use super::{Coordinate, Grid, Player};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// One rule for picking a move. A pipeline asks each of its strategies in turn, and the
+// first to suggest a move gets it played.
+pub trait Strategy {
+    // The name the strategy goes by in a pipeline config, eg. `block-fork`
+    fn name(&self) -> &'static str;
+
+    // Whether this move is one the strategy would suggest.
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool;
+
+    // The first candidate move the strategy accepts.
+    fn choose(&self, context: &Context) -> Option<Coordinate> {
+        context
+            .moves
+            .iter()
+            .find(|c| self.accepts(context, c))
+            .copied()
+    }
+}
+
+// What a strategy gets to look at. The candidate moves are usually every legal move, in
+// a random order so that strategies picking the first match don't always play the same.
+pub struct Context<'a> {
+    grid: &'a Grid,
+    player: Player,
+    moves: &'a [Coordinate],
+    opponents: Vec<Player>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(grid: &'a Grid, player: Player, moves: &'a [Coordinate]) -> Self {
+        // With more than two players, anyone else on the board is a threat. The next
+        // player in a two-player game is always considered, even before they've moved.
+        let opponents = player
+            .opponents()
+            .filter(|p| *p == player.turn() || grid.count(p) > 0)
+            .collect();
+
+        Self {
+            grid,
+            player,
+            moves,
+            opponents,
+        }
+    }
+
+    pub fn get_grid(&self) -> &Grid {
+        self.grid
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_moves(&self) -> &[Coordinate] {
+        self.moves
+    }
+
+    pub fn get_opponents(&self) -> &[Player] {
+        &self.opponents
+    }
+}
+
+// Can I make a winning move?
+pub struct Win;
+
+// Can my opponent make a winning move?
+pub struct Block;
+
+// Can I be cunning and fork the other player?
+pub struct Fork;
+
+// Can the other player make a legal move that will cause me to be forked?
+pub struct BlockFork;
+
+// Can I at least make a move that will put the other player in check?
+pub struct Check;
+
+// Take the middle of the board, if it has one.
+pub struct Center;
+
+pub struct Corner;
+
+// A side space that isn't a corner.
+pub struct Edge;
+
+// Take the corner across from one an opponent holds.
+pub struct OppositeCorner;
+
+impl Strategy for Win {
+    fn name(&self) -> &'static str {
+        "win"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        context.grid.wins_with(coordinate, &context.player)
+    }
+}
+
+impl Strategy for Block {
+    fn name(&self) -> &'static str {
+        "block"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        context
+            .opponents
+            .iter()
+            .any(|opponent| context.grid.wins_with(coordinate, opponent))
+    }
+
+    // The first opponent with a winning move gets blocked first.
+    fn choose(&self, context: &Context) -> Option<Coordinate> {
+        context
+            .opponents
+            .iter()
+            .find_map(|opponent| winning_move(context.grid, context.moves, opponent))
+    }
+}
+
+impl Strategy for Fork {
+    fn name(&self) -> &'static str {
+        "fork"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        forks(context.grid, context.moves, coordinate, &context.player)
+    }
+}
+
+impl Strategy for BlockFork {
+    fn name(&self) -> &'static str {
+        "block-fork"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        context
+            .opponents
+            .iter()
+            .any(|opponent| forks(context.grid, context.moves, coordinate, opponent))
+    }
+
+    // The first opponent with a fork gets blocked first.
+    fn choose(&self, context: &Context) -> Option<Coordinate> {
+        context.opponents.iter().find_map(|opponent| {
+            context
+                .moves
+                .iter()
+                .find(|c| forks(context.grid, context.moves, c, opponent))
+                .copied()
+        })
+    }
+}
+
+impl Strategy for Check {
+    fn name(&self) -> &'static str {
+        "check"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        let next_grid = try_move(context.grid, coordinate, &context.player);
+        context
+            .moves
+            .iter()
+            .any(|next| next != coordinate && next_grid.wins_with(next, &context.player))
+    }
+}
+
+impl Strategy for Center {
+    fn name(&self) -> &'static str {
+        "center"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        2 * coordinate.x() + 1 == context.grid.width()
+            && 2 * coordinate.y() + 1 == context.grid.height()
+    }
+}
+
+impl Strategy for Corner {
+    fn name(&self) -> &'static str {
+        "corner"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        opposite_corner(context.grid, coordinate).is_some()
+    }
+}
+
+impl Strategy for Edge {
+    fn name(&self) -> &'static str {
+        "edge"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        let (last_x, last_y) = (context.grid.width() - 1, context.grid.height() - 1);
+        let (x, y) = (coordinate.x(), coordinate.y());
+        (x == 0 || y == 0 || x == last_x || y == last_y)
+            && opposite_corner(context.grid, coordinate).is_none()
+    }
+}
+
+impl Strategy for OppositeCorner {
+    fn name(&self) -> &'static str {
+        "opposite-corner"
+    }
+
+    fn accepts(&self, context: &Context, coordinate: &Coordinate) -> bool {
+        opposite_corner(context.grid, coordinate)
+            .and_then(|opposite| context.grid.get_space(&opposite).get_player())
+            .is_some_and(|owner| context.opponents.contains(&owner))
+    }
+}
+
+// Strategies in the order they're asked. Players can be put together in code:
+//
+//     Pipeline::new().with(Win).with(Block).with(Center)
+//
+// or from a config, with one strategy name per line or separated by commas:
+//
+//     # Cautious
+//     win, block, center, opposite-corner, corner
+#[derive(Default)]
+pub struct Pipeline {
+    strategies: Vec<Box<dyn Strategy>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // `ComputerPlayer`'s usual chain: win, block, fork, block fork, then check.
+    pub fn classic() -> Self {
+        Self::new()
+            .with(Win)
+            .with(Block)
+            .with(Fork)
+            .with(BlockFork)
+            .with(Check)
+    }
+
+    pub fn with<S: Strategy + 'static>(mut self, strategy: S) -> Self {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    pub fn with_boxed(mut self, strategy: Box<dyn Strategy>) -> Self {
+        self.strategies.push(strategy);
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    pub fn get_strategies(&self) -> &[Box<dyn Strategy>] {
+        &self.strategies
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.strategies.iter().map(|s| s.name()).collect()
+    }
+
+    // The first move suggested by any of the first `steps` strategies, along with the
+    // strategy that suggested it.
+    pub fn choose(&self, context: &Context, steps: usize) -> Option<(Coordinate, &dyn Strategy)> {
+        self.strategies
+            .iter()
+            .take(steps)
+            .find_map(|s| s.choose(context).map(|c| (c, s.as_ref())))
+    }
+}
+
+// The built-in strategy with this name.
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    let strategy: Box<dyn Strategy> = match name {
+        "win" => Box::new(Win),
+        "block" => Box::new(Block),
+        "fork" => Box::new(Fork),
+        "block-fork" => Box::new(BlockFork),
+        "check" => Box::new(Check),
+        "center" => Box::new(Center),
+        "corner" => Box::new(Corner),
+        "edge" => Box::new(Edge),
+        "opposite-corner" => Box::new(OppositeCorner),
+        _ => return None,
+    };
+    Some(strategy)
+}
+
+impl FromStr for Pipeline {
+    type Err = FileError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let mut pipeline = Pipeline::new();
+
+        for (number, line) in raw.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+
+            for name in line.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+                let strategy = by_name(name).ok_or_else(|| {
+                    FileError::Parse("pipeline", number + 1, format!("unknown strategy {}", name))
+                })?;
+                pipeline = pipeline.with_boxed(strategy);
+            }
+        }
+
+        Ok(pipeline)
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+fn try_move(grid: &Grid, coordinate: &Coordinate, player: &Player) -> Grid {
+    let mut grid = grid.clone();
+    grid.set_space(coordinate, player).ok(); // This is okay.
+    grid
+}
+
+fn winning_move(grid: &Grid, moves: &[Coordinate], player: &Player) -> Option<Coordinate> {
+    moves.iter().find(|c| grid.wins_with(c, player)).copied()
+}
+
+// Would playing here leave the player with two or more of these moves to win with?
+fn forks(grid: &Grid, moves: &[Coordinate], coordinate: &Coordinate, player: &Player) -> bool {
+    let next_grid = try_move(grid, coordinate, player);
+    moves
+        .iter()
+        .filter(|next| *next != coordinate && next_grid.wins_with(next, player))
+        .count()
+        > 1
+}
+
+// The corner diagonally across the board, if this is a corner.
+fn opposite_corner(grid: &Grid, coordinate: &Coordinate) -> Option<Coordinate> {
+    let (last_x, last_y) = (grid.width() - 1, grid.height() - 1);
+    match (coordinate.x(), coordinate.y()) {
+        (x, y) if (x == 0 || x == last_x) && (y == 0 || y == last_y) => {
+            Some(Coordinate::new(last_x - x, last_y - y))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test_strategy {
+    use super::{Block, Center, Context, Corner, Edge, OppositeCorner, Pipeline, Strategy, Win};
+    use crate::game::{Coordinate, Grid, Player};
+
+    fn grid(x: &[&str], o: &[&str]) -> Grid {
+        let mut grid = Grid::empty();
+        for (player, moves) in [(Player::X, x), (Player::O, o)].iter() {
+            for raw in moves.iter() {
+                grid.set_space(&raw.parse().unwrap(), player).unwrap();
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn board_strategies() {
+        let empty = Grid::empty();
+        let moves = empty.legal_moves();
+        let context = Context::new(&empty, Player::X, &moves);
+        assert_eq!(Some(Coordinate::new(1, 1)), Center.choose(&context));
+        assert_eq!(Some(Coordinate::new(0, 0)), Corner.choose(&context));
+        assert_eq!(None, OppositeCorner.choose(&context));
+        assert_eq!(Some(Coordinate::new(0, 1)), Edge.choose(&context));
+        assert!(!Edge.accepts(&context, &Coordinate::new(1, 1)));
+
+        let grid = grid(&["B2"], &["A3"]);
+        let moves = grid.legal_moves();
+        let context = Context::new(&grid, Player::O, &moves);
+        assert_eq!(None, Center.choose(&context));
+        assert_eq!(None, OppositeCorner.choose(&context));
+
+        let context = Context::new(&grid, Player::X, &moves);
+        assert_eq!(Some(Coordinate::new(2, 0)), OppositeCorner.choose(&context));
+    }
+
+    #[test]
+    fn order_matters() {
+        // XX.
+        // OO.
+        // ...
+        let grid = grid(&["A1", "B1"], &["A2", "B2"]);
+        let moves = grid.legal_moves();
+        let context = Context::new(&grid, Player::X, &moves);
+
+        let pipeline = Pipeline::new().with(Win).with(Block);
+        let (coordinate, strategy) = pipeline.choose(&context, usize::MAX).unwrap();
+        assert_eq!(Coordinate::new(2, 0), coordinate);
+        assert_eq!("win", strategy.name());
+
+        let pipeline = Pipeline::new().with(Block).with(Win);
+        assert_eq!(
+            Coordinate::new(2, 1),
+            pipeline.choose(&context, usize::MAX).unwrap().0,
+        );
+
+        // Only the first `steps` strategies get asked.
+        let pipeline = Pipeline::new().with(Center).with(Win);
+        assert!(pipeline.choose(&context, 1).is_none());
+        assert_eq!(
+            Some(Coordinate::new(2, 0)),
+            pipeline.choose(&context, 2).map(|(c, _)| c),
+        );
+    }
+
+    #[test]
+    fn from_config() {
+        let pipeline: Pipeline = "
+            # Cautious
+            win, block
+            center,opposite-corner
+            corner
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(
+            vec!["win", "block", "center", "opposite-corner", "corner"],
+            pipeline.names(),
+        );
+        assert_eq!(
+            vec!["win", "block", "fork", "block-fork", "check"],
+            Pipeline::classic().names(),
+        );
+
+        assert_eq!(
+            "Invalid pipeline on line 2: unknown strategy side",
+            &format!("{}", "win\nblock, side".parse::<Pipeline>().unwrap_err()),
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use super::game::Space;
+use super::{Coordinate, Grid};
+use std::collections::HashSet;
+
+// One of the eight ways to turn or flip a square board onto itself: an optional swap of
+// rows and columns, followed by optional mirrors left-to-right and top-to-bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symmetry {
+    transpose: bool,
+    flip_x: bool,
+    flip_y: bool,
+}
+
+impl Symmetry {
+    pub const IDENTITY: Symmetry = Symmetry {
+        transpose: false,
+        flip_x: false,
+        flip_y: false,
+    };
+
+    pub fn all() -> impl Iterator<Item = Symmetry> {
+        (0..8).map(|i| Symmetry {
+            transpose: i & 4 != 0,
+            flip_x: i & 2 != 0,
+            flip_y: i & 1 != 0,
+        })
+    }
+
+    // Where a space ends up on a board `size` spaces across.
+    pub fn apply(&self, coordinate: &Coordinate, size: usize) -> Coordinate {
+        let (mut x, mut y) = (coordinate.x(), coordinate.y());
+        if self.transpose {
+            std::mem::swap(&mut x, &mut y);
+        }
+        if self.flip_x {
+            x = size - 1 - x;
+        }
+        if self.flip_y {
+            y = size - 1 - y;
+        }
+        Coordinate::new(x, y)
+    }
+
+    // Where a space came from, ie. the opposite of `apply()`.
+    pub fn invert(&self, coordinate: &Coordinate, size: usize) -> Coordinate {
+        let (mut x, mut y) = (coordinate.x(), coordinate.y());
+        if self.flip_x {
+            x = size - 1 - x;
+        }
+        if self.flip_y {
+            y = size - 1 - y;
+        }
+        if self.transpose {
+            std::mem::swap(&mut x, &mut y);
+        }
+        Coordinate::new(x, y)
+    }
+
+    // The board's key after turning it by this symmetry.
+    pub fn key(&self, grid: &Grid) -> String {
+        let size = grid.width();
+        (0..grid.height())
+            .flat_map(|y| (0..size).map(move |x| Coordinate::new(x, y)))
+            .map(|c| symbol(grid.get_space(&self.invert(&c, size))))
+            .collect()
+    }
+}
+
+// One character per space, row by row: `X`, `O`, `Δ`, `□`, `#` for blocked and `.` for
+// empty.
+pub fn key(grid: &Grid) -> String {
+    Symmetry::IDENTITY.key(grid)
+}
+
+// The smallest key among all eight turns of a square board, and the symmetry that turns
+// the board into it. Boards that are turns of each other share a canonical key, so
+// anything learned about one applies to them all.
+pub fn canonical(grid: &Grid) -> (String, Symmetry) {
+    assert_eq!(
+        grid.width(),
+        grid.height(),
+        "Only square boards can be turned"
+    );
+
+    Symmetry::all()
+        .map(|symmetry| (symmetry.key(grid), symmetry))
+        .min_by(|(a, _), (b, _)| a.cmp(b))
+        .unwrap()
+}
+
+// Whether every turn and flip of the board maps its winning sets onto each other, as it
+// does for straight lines on a square board. Only then do turned boards play the same,
+// so that a canonical key stands for all of them.
+pub fn is_symmetric(grid: &Grid) -> bool {
+    if grid.width() != grid.height() {
+        return false;
+    }
+
+    let size = grid.width();
+    let lines: Vec<Vec<Coordinate>> = grid.lines().map(|l| l.get_coordinates()).collect();
+    let sets: HashSet<Vec<(usize, usize)>> =
+        lines.iter().map(|line| set(line.iter().copied())).collect();
+
+    Symmetry::all().all(|symmetry| {
+        lines
+            .iter()
+            .all(|line| sets.contains(&set(line.iter().map(|c| symmetry.apply(c, size)))))
+    })
+}
+
+// The spaces as a set that compares equal however they're ordered.
+fn set(coordinates: impl Iterator<Item = Coordinate>) -> Vec<(usize, usize)> {
+    let mut set: Vec<(usize, usize)> = coordinates.map(|c| (c.y(), c.x())).collect();
+    set.sort_unstable();
+    set
+}
+
+fn symbol(space: Space) -> char {
+    match space {
+        Space::Empty => '.',
+        Space::Blocked => '#',
+        space => format!("{}", space).chars().next().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod test_symmetry {
+    use super::{canonical, is_symmetric, key, Symmetry};
+    use crate::game::{Coordinate, Grid, Player};
+
+    #[test]
+    fn apply_and_invert() {
+        for symmetry in Symmetry::all() {
+            for c in Grid::with_size(4, 4, 3).coordinates() {
+                assert_eq!(c, symmetry.invert(&symmetry.apply(&c, 4), 4));
+            }
+        }
+        assert_eq!(8, Symmetry::all().count());
+    }
+
+    #[test]
+    fn turned_boards_share_a_key() {
+        // X..   ..X   ...
+        // .O.   .O.   .O.
+        // ...   ...   ..X
+        let boards: Vec<Grid> = ["A1", "C1", "C3", "A3"]
+            .iter()
+            .map(|corner| {
+                let mut grid = Grid::empty();
+                grid.set_space(&corner.parse().unwrap(), &Player::X)
+                    .unwrap();
+                grid.set_space(&Coordinate::new(1, 1), &Player::O).unwrap();
+                grid
+            })
+            .collect();
+
+        let (first, _) = canonical(&boards[0]);
+        for grid in boards.iter() {
+            let (canonical_key, symmetry) = canonical(grid);
+            assert_eq!(first, canonical_key);
+            assert_eq!(canonical_key, symmetry.key(grid));
+        }
+        assert_eq!("....O...X", &first);
+        assert_eq!("X...O....", &key(&boards[0]));
+    }
+
+    #[test]
+    fn canonical_moves() {
+        // .X.
+        // ...
+        // ...   is stored with X on B3 instead, so a move on A1 of the stored board is
+        //       really A3 here.
+        let mut grid = Grid::empty();
+        grid.set_space(&"B1".parse().unwrap(), &Player::X).unwrap();
+        let (canonical_key, symmetry) = canonical(&grid);
+        assert_eq!(".......X.", &canonical_key);
+
+        let coordinate = |raw: &str| raw.parse::<Coordinate>().unwrap();
+        assert_eq!(coordinate("B3"), symmetry.apply(&coordinate("B1"), 3));
+        assert_eq!(coordinate("A3"), symmetry.invert(&coordinate("A1"), 3));
+    }
+
+    #[test]
+    fn symmetric_lines() {
+        assert!(is_symmetric(&Grid::empty()));
+        assert!(is_symmetric(&Grid::with_size(5, 5, 4)));
+        assert!(!is_symmetric(&Grid::with_size(4, 3, 3)));
+
+        // Only the top row wins, so turning the board changes the game.
+        let row = ["A1", "B1", "C1"]
+            .iter()
+            .map(|raw| raw.parse().unwrap())
+            .collect();
+        assert!(!is_symmetric(&Grid::with_winning_sets(3, 3, vec![row])));
+    }
+}
@@ -0,0 +1,511 @@
+use super::game::{ParseCoordinateError, Shape};
+use super::rando::RandoPlayer;
+use super::rules::{self, Agent, Outcome, Rules};
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+// Teeko: each player drops four pieces on a 5x5 board, then takes turns moving one of
+// them to a neighbouring empty space. Four in a row wins, and so do four pieces in a 2x2
+// square.
+pub const SIZE: usize = 5;
+pub const PIECES: usize = 4;
+
+pub fn grid() -> Grid {
+    let mut shapes = Shape::lines(4);
+    shapes.push(Shape::square(2));
+    Grid::with_shapes(SIZE, SIZE, &shapes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeekoMove {
+    // Placing a new piece, while the player still has some in hand
+    Drop(Coordinate),
+
+    // Moving a piece from one space to a neighbouring one
+    Step(Coordinate, Coordinate),
+}
+
+// Drops are written like any other move, eg. "C3", and steps as "C3-D4".
+impl FromStr for TeekoMove {
+    type Err = ParseCoordinateError;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let raw = raw.trim();
+        match raw.split_once('-') {
+            Some((from, to)) => Ok(TeekoMove::Step(from.trim().parse()?, to.trim().parse()?)),
+            None => Ok(TeekoMove::Drop(raw.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for TeekoMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TeekoMove::Drop(coordinate) => write!(f, "{}", coordinate),
+            TeekoMove::Step(from, to) => write!(f, "{}-{}", from, to),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct IllegalTeekoMove(TeekoMove);
+
+impl fmt::Display for IllegalTeekoMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not a legal move.", self.0)
+    }
+}
+
+// Drops until the player's pieces are all on the board, then steps.
+pub fn legal_moves(grid: &Grid, player: &Player) -> Vec<TeekoMove> {
+    if grid.count(player) < PIECES {
+        return grid
+            .legal_moves()
+            .into_iter()
+            .map(TeekoMove::Drop)
+            .collect();
+    }
+
+    grid.coordinates()
+        .filter(|c| grid.get_space(c).get_player() == Some(*player))
+        .flat_map(|from| {
+            grid.neighbours(&from)
+                .into_iter()
+                .filter(|to| grid.is_legal(to))
+                .map(move |to| TeekoMove::Step(from, to))
+        })
+        .collect()
+}
+
+pub fn apply(
+    grid: &mut Grid,
+    player: &Player,
+    teeko_move: &TeekoMove,
+) -> Result<(), IllegalTeekoMove> {
+    if !legal_moves(grid, player).contains(teeko_move) {
+        return Err(IllegalTeekoMove(*teeko_move));
+    }
+
+    make_move(grid, player, teeko_move);
+    Ok(())
+}
+
+// Applies a move already known to be legal.
+fn make_move(grid: &mut Grid, player: &Player, teeko_move: &TeekoMove) {
+    match teeko_move {
+        TeekoMove::Drop(to) => grid.set_space(to, player).ok(),
+        TeekoMove::Step(from, to) => {
+            grid.clear_space(from);
+            grid.set_space(to, player).ok()
+        }
+    };
+}
+
+// Teeko under the generic rules, so the shared game loop and players can take it on.
+// Pieces can shuffle around forever, so the game is a draw once `max_moves` moves have
+// been made without a winner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Teeko {
+    max_moves: usize,
+}
+
+impl Teeko {
+    pub fn new(max_moves: usize) -> Self {
+        Self { max_moves }
+    }
+}
+
+// A Teeko position, and how many moves it took to reach it, which decides when the game
+// is called a draw.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TeekoPosition {
+    grid: Grid,
+    player: Player,
+    moves: usize,
+}
+
+impl TeekoPosition {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn get_player(&self) -> Player {
+        self.player
+    }
+
+    pub fn get_moves(&self) -> usize {
+        self.moves
+    }
+}
+
+impl Rules for Teeko {
+    type State = TeekoPosition;
+    type Move = TeekoMove;
+    type Outcome = Outcome;
+
+    fn initial_state(&self) -> TeekoPosition {
+        TeekoPosition {
+            grid: grid(),
+            player: Player::X,
+            moves: 0,
+        }
+    }
+
+    fn to_move(&self, state: &TeekoPosition) -> Player {
+        state.player
+    }
+
+    fn legal_moves(&self, state: &TeekoPosition) -> Vec<TeekoMove> {
+        legal_moves(&state.grid, &state.player)
+    }
+
+    fn apply(&self, state: &TeekoPosition, chosen: &TeekoMove) -> TeekoPosition {
+        let mut grid = state.grid.clone();
+        apply(&mut grid, &state.player, chosen).expect("Illegal move!");
+        TeekoPosition {
+            grid,
+            player: state.player.turn(),
+            moves: state.moves + 1,
+        }
+    }
+
+    fn outcome(&self, state: &TeekoPosition) -> Option<Outcome> {
+        match state.grid.get_winner() {
+            Some(winner) => Some(Outcome::Win(winner)),
+            None if state.moves >= self.max_moves => Some(Outcome::Draw),
+            None => None,
+        }
+    }
+
+    fn payoff(&self, outcome: &Outcome, player: &Player) -> f64 {
+        outcome.payoff(player)
+    }
+}
+
+pub trait TeekoPlayable {
+    fn play(&mut self, grid: &Grid, player: &Player) -> TeekoMove;
+
+    // Restarts any random choices the player makes from `seed`, as `Playable::reseed()`.
+    fn reseed(&mut self, _seed: u64) {}
+}
+
+// Lets any `TeekoPlayable` take a seat under the generic rules.
+impl Agent<Teeko> for Box<dyn TeekoPlayable> {
+    fn play(&mut self, _rules: &Teeko, state: &TeekoPosition) -> TeekoMove {
+        TeekoPlayable::play(self.as_mut(), &state.grid, &state.player)
+    }
+}
+
+// Plays a game of Teeko, X first, calling it a draw after `max_moves` moves.
+pub fn play(
+    player_x: Box<dyn TeekoPlayable>,
+    player_o: Box<dyn TeekoPlayable>,
+    max_moves: usize,
+) -> TeekoResult {
+    let record = rules::play(
+        &Teeko::new(max_moves),
+        vec![
+            (Player::X, Box::new(player_x)),
+            (Player::O, Box::new(player_o)),
+        ],
+    );
+
+    TeekoResult {
+        grid: record.get_state().get_grid().clone(),
+        moves: record.get_moves().to_vec(),
+    }
+}
+
+// Plays a game with both players reseeded from one seed, so it replays exactly.
+pub fn play_seeded(
+    mut player_x: Box<dyn TeekoPlayable>,
+    mut player_o: Box<dyn TeekoPlayable>,
+    max_moves: usize,
+    seed: u64,
+) -> TeekoResult {
+    let mut rng = StdRng::seed_from_u64(seed);
+    player_x.reseed(rng.gen());
+    player_o.reseed(rng.gen());
+    play(player_x, player_o, max_moves)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TeekoResult {
+    grid: Grid,
+    moves: Vec<(Player, TeekoMove)>,
+}
+
+impl TeekoResult {
+    pub fn get_grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn get_moves(&self) -> &[(Player, TeekoMove)] {
+        &self.moves
+    }
+
+    pub fn get_winner(&self) -> Option<Player> {
+        self.grid.get_winner()
+    }
+}
+
+impl TeekoPlayable for RandoPlayer {
+    fn play(&mut self, grid: &Grid, player: &Player) -> TeekoMove {
+        *self
+            .choose(&legal_moves(grid, player))
+            .expect("No legal moves!")
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        Playable::reseed(self, seed);
+    }
+}
+
+// Takes a winning move when there is one, and otherwise plays at random among the moves
+// that don't hand the opponent a win on their next move.
+pub struct TeekoComputerPlayer {
+    rng: StdRng,
+}
+
+impl TeekoComputerPlayer {
+    pub fn new() -> Self {
+        Self::seeded(thread_rng().gen())
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn try_move(grid: &Grid, player: &Player, teeko_move: &TeekoMove) -> Grid {
+        let mut grid = grid.clone();
+        make_move(&mut grid, player, teeko_move);
+        grid
+    }
+
+    // Only patterns through the space moved to can have been completed.
+    fn wins_with(grid: &Grid, player: &Player, teeko_move: &TeekoMove) -> bool {
+        let to = match teeko_move {
+            TeekoMove::Drop(to) | TeekoMove::Step(_, to) => to,
+        };
+        Self::try_move(grid, player, teeko_move)
+            .lines_through(to)
+            .any(|line| line.get_winner() == Some(*player))
+    }
+}
+
+impl Default for TeekoComputerPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TeekoPlayable for TeekoComputerPlayer {
+    fn play(&mut self, grid: &Grid, player: &Player) -> TeekoMove {
+        let moves = legal_moves(grid, player);
+        let opponent = player.turn();
+
+        if let Some(winning_move) = moves.iter().find(|m| Self::wins_with(grid, player, m)) {
+            return *winning_move;
+        }
+
+        let safe_moves: Vec<TeekoMove> = moves
+            .iter()
+            .copied()
+            .filter(|m| {
+                let next_grid = Self::try_move(grid, player, m);
+                !legal_moves(&next_grid, &opponent)
+                    .iter()
+                    .any(|reply| Self::wins_with(&next_grid, &opponent, reply))
+            })
+            .collect();
+
+        let candidates = if safe_moves.is_empty() {
+            &moves
+        } else {
+            &safe_moves
+        };
+        *candidates.choose(&mut self.rng).expect("No legal moves!")
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+// Takes moves from the keyboard, eg. "C3" to drop a piece or "C3-D4" to move one.
+pub struct TeekoHumanPlayer;
+
+impl TeekoPlayable for TeekoHumanPlayer {
+    fn play(&mut self, grid: &Grid, player: &Player) -> TeekoMove {
+        loop {
+            println!("{}", grid);
+            println!();
+            if grid.count(player) < PIECES {
+                println!("Enter {} move (eg. C3):", player);
+            } else {
+                println!("Enter {} move (eg. C3-D4):", player);
+            }
+
+            let mut buf = String::new();
+            io::stdin().read_line(&mut buf).unwrap();
+
+            match buf.parse::<TeekoMove>() {
+                Ok(teeko_move) if legal_moves(grid, player).contains(&teeko_move) => {
+                    break teeko_move
+                }
+                Ok(teeko_move) => println!("{}", IllegalTeekoMove(teeko_move)),
+                Err(e) => println!("{}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_teeko {
+    use super::{
+        apply, grid, legal_moves, play_seeded, IllegalTeekoMove, Teeko, TeekoComputerPlayer,
+        TeekoMove, TeekoPlayable,
+    };
+    use crate::game::{Coordinate, Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::rules::{self, MinimaxPlayer, Outcome};
+
+    fn drop_all(grid: &mut Grid, player: &Player, moves: &[&str]) {
+        for raw in moves {
+            let coordinate = raw.parse().unwrap();
+            apply(grid, player, &TeekoMove::Drop(coordinate)).unwrap();
+        }
+    }
+
+    #[test]
+    fn winning_patterns() {
+        let grid = grid();
+
+        // 10 rows and 10 columns of four, 4 diagonals each way, and 16 squares
+        assert_eq!(44, grid.lines().len());
+
+        let mut grid = super::grid();
+        drop_all(&mut grid, &Player::O, &["B2", "C2", "B3"]);
+        assert!(grid.wins_with(&"C3".parse().unwrap(), &Player::O));
+        assert!(!grid.wins_with(&"D3".parse().unwrap(), &Player::O));
+    }
+
+    #[test]
+    fn drop_then_step() {
+        let mut grid = grid();
+        assert_eq!(25, legal_moves(&grid, &Player::X).len());
+
+        drop_all(&mut grid, &Player::X, &["A1", "C1", "E1", "A5"]);
+        let moves = legal_moves(&grid, &Player::X);
+        assert!(moves.iter().all(|m| matches!(m, TeekoMove::Step(_, _))));
+
+        // A1 and E1 have 3 neighbours each, C1 and A5 have 5 and 3
+        assert_eq!(14, moves.len());
+
+        let step: TeekoMove = "A1-B2".parse().unwrap();
+        apply(&mut grid, &Player::X, &step).unwrap();
+        assert_eq!(None, grid.get_space(&Coordinate::new(0, 0)).get_player());
+        assert_eq!(
+            Some(Player::X),
+            grid.get_space(&Coordinate::new(1, 1)).get_player(),
+        );
+
+        let jump: TeekoMove = "B2-D4".parse().unwrap();
+        assert_eq!(
+            Err(IllegalTeekoMove(jump)),
+            apply(&mut grid, &Player::X, &jump)
+        );
+        assert_eq!(
+            Err(IllegalTeekoMove(TeekoMove::Drop(Coordinate::new(2, 2)))),
+            apply(&mut grid, &Player::X, &"C3".parse().unwrap()),
+        );
+    }
+
+    #[test]
+    fn notation() {
+        assert_eq!(Ok(TeekoMove::Drop(Coordinate::new(2, 2))), "C3\n".parse());
+        assert_eq!(
+            Ok(TeekoMove::Step(
+                Coordinate::new(2, 2),
+                Coordinate::new(3, 3)
+            )),
+            "c3-d4".parse(),
+        );
+        assert!("C3-".parse::<TeekoMove>().is_err());
+        assert_eq!(
+            "C3-D4",
+            &format!("{}", "C3-D4".parse::<TeekoMove>().unwrap())
+        );
+        assert_eq!(
+            "B2-D4 is not a legal move.",
+            &format!("{}", IllegalTeekoMove("B2-D4".parse().unwrap())),
+        );
+    }
+
+    #[test]
+    fn computer_wins_and_defends() {
+        // X can close the square by stepping from D3 to C3.
+        let mut grid = grid();
+        drop_all(&mut grid, &Player::X, &["B2", "C2", "B3", "D3"]);
+        drop_all(&mut grid, &Player::O, &["D4", "E4", "E5", "A5"]);
+
+        let mut player = TeekoComputerPlayer::seeded(1);
+        let teeko_move = player.play(&grid, &Player::X);
+        let mut next_grid = grid.clone();
+        apply(&mut next_grid, &Player::X, &teeko_move).unwrap();
+        assert!(next_grid.has_line(&Player::X));
+
+        // O can't win, so it has to step into C3 first.
+        for _ in 0..10 {
+            let teeko_move = player.play(&grid, &Player::O);
+            let mut next_grid = grid.clone();
+            apply(&mut next_grid, &Player::O, &teeko_move).unwrap();
+            assert!(!next_grid.is_legal(&Coordinate::new(2, 2)));
+        }
+    }
+
+    #[test]
+    fn computer_beats_random_player() {
+        let mut wins = 0;
+        let mut losses = 0;
+        for seed in 0..10 {
+            let result = play_seeded(
+                Box::new(TeekoComputerPlayer::new()),
+                Box::new(RandoPlayer::new()),
+                100,
+                seed,
+            );
+            match result.get_winner() {
+                Some(Player::X) => wins += 1,
+                Some(_) => losses += 1,
+                None => assert_eq!(100, result.get_moves().len()),
+            }
+        }
+        assert!(wins > losses);
+    }
+
+    #[test]
+    fn generic_rules() {
+        let record = rules::play(
+            &Teeko::new(40),
+            vec![
+                (Player::X, Box::new(MinimaxPlayer::new().with_depth(2))),
+                (Player::O, Box::new(RandoPlayer::new())),
+            ],
+        );
+
+        assert!(record.get_moves().len() <= 40);
+        match record.get_outcome() {
+            Outcome::Win(winner) => {
+                assert!(record.get_state().get_grid().has_line(winner))
+            }
+            Outcome::Draw => assert_eq!(40, record.get_moves().len()),
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use super::solver::Solver;
+use super::{Coordinate, Grid, Playable, Player};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+// How the trap setter expects its opponent to play.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Model {
+    // Any legal move, equally likely, as `RandoPlayer` plays
+    Uniform,
+    // One of the best moves, except that with this probability it's any legal move
+    Noisy(f64),
+}
+
+impl Model {
+    // The chance of each legal move, given the opponent's best moves.
+    fn odds(&self, moves: &[Coordinate], best_moves: &[Coordinate]) -> Vec<(Coordinate, f64)> {
+        let uniform = 1.0 / moves.len() as f64;
+        match self {
+            Model::Uniform => moves.iter().map(|c| (*c, uniform)).collect(),
+            Model::Noisy(noise) => {
+                let best = (1.0 - noise) / best_moves.len() as f64;
+                moves
+                    .iter()
+                    .map(|c| {
+                        let p = noise * uniform;
+                        (*c, if best_moves.contains(c) { p + best } else { p })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+// A perfect player that sets traps. It only plays moves that keep the best result, like
+// `ComputerPlayer` at the `Perfect` level, but among those it picks the one that gives a
+// fallible opponent the most chances to go wrong: the move with the highest probability
+// of the opponent losing, if they play by the model and it keeps setting traps. Every
+// position is solved and cached, so it's only practical on small two-player boards.
+#[derive(Clone)]
+pub struct TrapPlayer {
+    player: Player,
+    model: Model,
+    solver: Solver,
+    // Chance of the opponent losing from each position, with the player to move
+    values: HashMap<(Grid, Player), f64>,
+    rng: StdRng,
+}
+
+impl TrapPlayer {
+    const EPSILON: f64 = 1e-12;
+
+    pub fn new(player: Player) -> Self {
+        Self {
+            player,
+            model: Model::Uniform,
+            solver: Solver::new(),
+            values: HashMap::new(),
+            rng: StdRng::seed_from_u64(thread_rng().gen()),
+        }
+    }
+
+    pub fn with_model(mut self, model: Model) -> Self {
+        if let Model::Noisy(noise) = model {
+            assert!((0.0..=1.0).contains(&noise), "Noise is a probability");
+        }
+        self.model = model;
+        self.values.clear();
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = StdRng::seed_from_u64(seed);
+        self
+    }
+
+    // The chance of the opponent losing after each safe move.
+    pub fn traps(&mut self, grid: &Grid) -> Vec<(Coordinate, f64)> {
+        let player = self.player;
+        self.solver
+            .best_moves(grid, &player)
+            .into_iter()
+            .map(|coordinate| {
+                let mut next_grid = grid.clone();
+                next_grid.set_space(&coordinate, &player).unwrap();
+                (coordinate, self.loss_probability(&next_grid, player.turn()))
+            })
+            .collect()
+    }
+
+    // The chance of the opponent losing from here, with `mover` to play.
+    pub fn loss_probability(&mut self, grid: &Grid, mover: Player) -> f64 {
+        if grid.has_won(&self.player) {
+            return 1.0;
+        } else if !grid.is_in_progress() {
+            return 0.0;
+        }
+
+        let key = (grid.clone(), mover);
+        if let Some(value) = self.values.get(&key) {
+            return *value;
+        }
+
+        let value = if mover == self.player {
+            self.traps(grid)
+                .into_iter()
+                .map(|(_, p)| p)
+                .fold(0.0, f64::max)
+        } else {
+            let moves = grid.legal_moves();
+            let best_moves = match self.model {
+                Model::Uniform => Vec::new(),
+                Model::Noisy(_) => self.solver.best_moves(grid, &mover),
+            };
+            self.model
+                .odds(&moves, &best_moves)
+                .into_iter()
+                .map(|(coordinate, p)| {
+                    let mut next_grid = grid.clone();
+                    next_grid.set_space(&coordinate, &mover).unwrap();
+                    p * self.loss_probability(&next_grid, mover.turn())
+                })
+                .sum()
+        };
+
+        self.values.insert(key, value);
+        value
+    }
+}
+
+impl Playable for TrapPlayer {
+    fn play(&mut self, grid: &Grid) -> Coordinate {
+        assert!(!grid.has_captures(), "The solver doesn't handle captures");
+
+        let traps = self.traps(grid);
+        let best = traps.iter().map(|(_, p)| *p).fold(0.0, f64::max);
+        let candidates: Vec<Coordinate> = traps
+            .into_iter()
+            .filter(|(_, p)| *p > best - Self::EPSILON)
+            .map(|(coordinate, _)| coordinate)
+            .collect();
+
+        *candidates.choose(&mut self.rng).expect("No legal moves!")
+    }
+
+    fn switch_sides(&mut self, player: Player) {
+        self.player = player;
+        self.values.clear();
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+#[cfg(test)]
+mod test_trap {
+    use super::{Model, TrapPlayer};
+    use crate::computer::ComputerPlayer;
+    use crate::game::{Grid, Player};
+    use crate::rando::RandoPlayer;
+    use crate::{play_seeded, Playable};
+
+    #[test]
+    fn prefers_likely_blunders() {
+        // Every opening keeps the draw, but a random opponent goes wrong most often
+        // after a corner.
+        let mut player = TrapPlayer::new(Player::X);
+        let traps = player.traps(&Grid::empty());
+        assert_eq!(9, traps.len());
+        assert!(traps.iter().all(|(_, p)| *p > 0.9));
+
+        let corners = [(0, 0), (2, 0), (0, 2), (2, 2)];
+        let opening = player.play(&Grid::empty());
+        assert!(corners.contains(&(opening.x(), opening.y())));
+
+        // A perfect opponent never loses.
+        let mut player = TrapPlayer::new(Player::X).with_model(Model::Noisy(0.0));
+        assert!(player.traps(&Grid::empty()).iter().all(|(_, p)| *p < 1e-9));
+
+        // One who only blunders sometimes loses less often.
+        let mut player = TrapPlayer::new(Player::X).with_model(Model::Noisy(0.2));
+        let noisy = player.loss_probability(&Grid::empty(), Player::X);
+        assert!(noisy > 0.0 && noisy < 0.9);
+    }
+
+    #[test]
+    fn wins_more_than_computer() {
+        let mut results = [[0; 2]; 2];
+
+        // Solve every position once, and share the cache between games.
+        let trap_players: Vec<TrapPlayer> = [Player::X, Player::O]
+            .iter()
+            .map(|side| {
+                let mut player = TrapPlayer::new(*side);
+                player.loss_probability(&Grid::empty(), Player::X);
+                player
+            })
+            .collect();
+
+        for seed in 0..100 {
+            for (i, side) in [Player::X, Player::O].iter().enumerate() {
+                let games: Vec<Box<dyn Playable>> = vec![
+                    Box::new(trap_players[i].clone()),
+                    Box::new(ComputerPlayer::new_silent(*side)),
+                ];
+                for (j, player) in games.into_iter().enumerate() {
+                    let grid = match side {
+                        Player::X => play_seeded(player, Box::new(RandoPlayer::new()), seed),
+                        _ => play_seeded(Box::new(RandoPlayer::new()), player, seed),
+                    };
+                    match grid.get_winner() {
+                        Some(winner) if winner == *side => results[j][i] += 1,
+                        Some(_) => assert_ne!(0, j, "The trap setter lost"),
+                        None => {}
+                    }
+                }
+            }
+        }
+
+        // Wins as X and as O
+        let [trap, computer] = results;
+        assert!(trap[0] > computer[0]);
+        assert!(trap[1] > computer[1]);
+    }
+
+    // Prints how the trap setter fares against a random player, from both sides, over the
+    // same seeded games as the `difficulty_levels` test in lib.rs.
+    #[test]
+    #[ignore]
+    fn against_random() {
+        for side in [Player::X, Player::O].iter() {
+            let mut player = TrapPlayer::new(*side);
+            player.loss_probability(&Grid::empty(), Player::X);
+            let mut results = [0; 3];
+
+            for seed in 0..1000 {
+                let trap = Box::new(player.clone());
+                let grid = match side {
+                    Player::X => play_seeded(trap, Box::new(RandoPlayer::new()), seed),
+                    _ => play_seeded(Box::new(RandoPlayer::new()), trap, seed),
+                };
+                results[match grid.get_winner() {
+                    Some(winner) if winner == *side => 0,
+                    None => 1,
+                    Some(_) => 2,
+                }] += 1;
+            }
+
+            println!(
+                "As {}: {}/{}/{} (wins/draws/losses)",
+                side, results[0], results[1], results[2],
+            );
+        }
+    }
+}
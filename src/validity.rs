@@ -0,0 +1,233 @@
+use super::{Grid, Player};
+use std::fmt;
+
+// Checks that a board could have come up in a two-player game started from an empty board,
+// with X moving first, and works out where the game stands.
+//
+// Removing stones never completes a line, so any board with sensible piece counts and no
+// line on it is reachable, one stone at a time. A board with a line is reachable as long
+// as the winner moved last, and one of their stones sits on every line they have.
+pub fn check(grid: &Grid) -> Result<Status, InvalidPosition> {
+    assert!(
+        !grid.has_captures(),
+        "Positions with captures can't be checked",
+    );
+
+    for player in [Player::Delta, Player::Square].iter() {
+        if grid.count(player) > 0 {
+            return Err(InvalidPosition::UnexpectedSymbol(*player));
+        }
+    }
+
+    let x = grid.count(&Player::X);
+    let o = grid.count(&Player::O);
+    let to_move = if x == o {
+        Player::X
+    } else if x == o + 1 {
+        Player::O
+    } else {
+        return Err(InvalidPosition::PieceCounts { x, o });
+    };
+
+    let winners: Vec<Player> = [Player::X, Player::O]
+        .iter()
+        .copied()
+        .filter(|p| grid.has_line(p))
+        .collect();
+
+    match winners.as_slice() {
+        [] if grid.has_legal_moves() => Ok(Status::ToMove(to_move)),
+        [] => Ok(Status::Drawn),
+        [winner] if *winner == to_move => Err(InvalidPosition::MoveAfterWin(*winner)),
+        [winner] if !has_final_move(grid, winner) => Err(InvalidPosition::NoFinalMove(*winner)),
+        [winner] => Ok(Status::Won(*winner)),
+        _ => Err(InvalidPosition::DoubleWinners),
+    }
+}
+
+pub fn is_reachable(grid: &Grid) -> bool {
+    check(grid).is_ok()
+}
+
+// Is there a stone that every one of the winner's lines runs through?
+fn has_final_move(grid: &Grid, winner: &Player) -> bool {
+    let lines: Vec<_> = grid
+        .lines()
+        .filter(|line| line.get_winner() == Some(*winner))
+        .map(|line| line.get_coordinates())
+        .collect();
+
+    lines[0]
+        .iter()
+        .any(|c| lines.iter().all(|line| line.contains(c)))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    // The game is still going, and it's this player's move.
+    ToMove(Player),
+    Won(Player),
+    Drawn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidPosition {
+    // Only X and O take part in a two-player game.
+    UnexpectedSymbol(Player),
+
+    // X moves first, so it has either as many stones as O or one more.
+    PieceCounts { x: usize, o: usize },
+
+    DoubleWinners,
+
+    // The game was already won by this player, but their opponent moved anyway.
+    MoveAfterWin(Player),
+
+    // This player's lines couldn't all have been completed by a single last move.
+    NoFinalMove(Player),
+}
+
+impl fmt::Display for InvalidPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidPosition::UnexpectedSymbol(player) => {
+                write!(f, "{} doesn't play in a two-player game.", player)
+            }
+            InvalidPosition::PieceCounts { x, o } => {
+                write!(f, "X has {} stones and O has {}, which can't happen.", x, o)
+            }
+            InvalidPosition::DoubleWinners => write!(f, "X and O can't both have a line."),
+            InvalidPosition::MoveAfterWin(player) => {
+                write!(
+                    f,
+                    "{} moved after {} had already won.",
+                    player.turn(),
+                    player
+                )
+            }
+            InvalidPosition::NoFinalMove(player) => {
+                write!(
+                    f,
+                    "No single move could have completed all of {}'s lines.",
+                    player
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_validity {
+    use super::{check, is_reachable, InvalidPosition, Status};
+    use crate::game::{Grid, Player, Space};
+
+    fn grid(width: usize, height: usize, x: &[&str], o: &[&str]) -> Grid {
+        let mut grid = Grid::with_size(width, height, 3);
+        for (player, moves) in [(Player::X, x), (Player::O, o)].iter() {
+            for raw in moves.iter() {
+                grid.set_space(&raw.parse().unwrap(), player).unwrap();
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn whose_turn() {
+        assert_eq!(Ok(Status::ToMove(Player::X)), check(&Grid::empty()));
+        assert_eq!(
+            Ok(Status::ToMove(Player::O)),
+            check(&grid(3, 3, &["B2"], &[])),
+        );
+        assert_eq!(
+            Ok(Status::ToMove(Player::X)),
+            check(&grid(3, 3, &["B2"], &["A1"])),
+        );
+    }
+
+    #[test]
+    fn finished_games() {
+        assert_eq!(
+            Ok(Status::Won(Player::O)),
+            check(&grid(3, 3, &["B2", "C2", "A3"], &["A1", "B1", "C1"])),
+        );
+        assert_eq!(
+            Ok(Status::Drawn),
+            check(&Grid::new([
+                [Space::X, Space::X, Space::O],
+                [Space::O, Space::O, Space::X],
+                [Space::X, Space::O, Space::X],
+            ])),
+        );
+
+        // X's row and column cross on A1, which was played last.
+        assert_eq!(
+            Ok(Status::Won(Player::X)),
+            check(&grid(
+                3,
+                3,
+                &["A1", "B1", "C1", "A2", "A3"],
+                &["B2", "C2", "B3", "C3"],
+            )),
+        );
+    }
+
+    #[test]
+    fn piece_counts() {
+        assert_eq!(
+            Err(InvalidPosition::PieceCounts { x: 5, o: 1 }),
+            check(&grid(3, 3, &["A1", "B1", "A2", "C2", "B3"], &["B2"])),
+        );
+        assert_eq!(
+            Err(InvalidPosition::PieceCounts { x: 0, o: 1 }),
+            check(&grid(3, 3, &[], &["B2"])),
+        );
+
+        let mut four_player = Grid::empty();
+        four_player
+            .set_space(&"A1".parse().unwrap(), &Player::Delta)
+            .unwrap();
+        assert_eq!(
+            Err(InvalidPosition::UnexpectedSymbol(Player::Delta)),
+            check(&four_player),
+        );
+    }
+
+    #[test]
+    fn impossible_wins() {
+        assert_eq!(
+            Err(InvalidPosition::DoubleWinners),
+            check(&grid(3, 3, &["A1", "B1", "C1"], &["A3", "B3", "C3"])),
+        );
+
+        // O replied after X's row was already complete.
+        assert_eq!(
+            Err(InvalidPosition::MoveAfterWin(Player::X)),
+            check(&grid(3, 3, &["A1", "B1", "C1"], &["A3", "B3", "B2"])),
+        );
+
+        // Two separate rows can't both have been finished by X's last move.
+        let position = grid(
+            5,
+            3,
+            &["A1", "B1", "C1", "A3", "B3", "C3"],
+            &["A2", "C2", "D2", "E1", "E3"],
+        );
+        assert_eq!(
+            Err(InvalidPosition::NoFinalMove(Player::X)),
+            check(&position)
+        );
+        assert!(!is_reachable(&position));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            "X has 5 stones and O has 1, which can't happen.",
+            &format!("{}", InvalidPosition::PieceCounts { x: 5, o: 1 }),
+        );
+        assert_eq!(
+            "O moved after X had already won.",
+            &format!("{}", InvalidPosition::MoveAfterWin(Player::X)),
+        );
+    }
+}